@@ -1,37 +1,71 @@
-use iced::{button, Align, Button, Container, Element, Length, Row, Text, scrollable, Scrollable, VerticalAlignment, HorizontalAlignment, Rule, Column, Space, Clipboard, Command};
+use iced::{button, Align, Button, Checkbox, Container, Element, Length, Row, Text, scrollable, Scrollable, VerticalAlignment, HorizontalAlignment, Rule, Column, Space, Clipboard, Command};
 // use futures::Future;
+use std::collections::{HashMap, HashSet};
 
-use crate::{style::ButtonStylingType, services::{bookmark_api::{BookmarkCategory, BookmarkApi}}};
+use crate::{style::ButtonStylingType, services::{bookmark_api::{BookmarkCategory, BookmarkApi, MarksQuery}}, utils};
 
 use super::state::{CategoryMessage, Events, State};
 use crate::style;
 
+const INDENT_WIDTH: u16 = 15;
+
 #[derive(Clone, Debug)]
 struct CategoryComponent {
     button_state: button::State,
+    toggle_state: button::State,
     data: BookmarkCategory, // bookmark_api: BookmarkApi
+    depth: u16,
+    has_children: bool,
+    archived: bool,
 }
 
 impl CategoryComponent {
-    pub fn new(data: BookmarkCategory) -> Self {
+    pub fn new(data: BookmarkCategory, depth: u16, has_children: bool, archived: bool) -> Self {
         CategoryComponent {
             button_state: button::State::new(),
+            toggle_state: button::State::new(),
             data,
+            depth,
+            has_children,
+            archived,
         }
     }
-    pub fn view(&mut self, index: usize) -> Element<CategoryMessage> {
+    pub fn view(&mut self, index: usize, expanded: bool) -> Element<CategoryMessage> {
+        let toggle: Element<CategoryMessage> = if self.has_children {
+            Button::new(&mut self.toggle_state, Text::new(if expanded { "-" } else { "+" })
+                .horizontal_alignment(HorizontalAlignment::Center)
+            )
+            .width(Length::Units(30))
+            .padding(10)
+            .style(style::StyledButton::primary())
+            .on_press(CategoryMessage::ToggleExpanded(self.data.id.to_owned()))
+            .into()
+        } else {
+            Space::new(Length::Units(30), Length::Shrink).into()
+        };
         let container: Container<CategoryMessage> = Container::new(
-            // Text::new(&self.data.name),
+            Row::new()
+            .push(Space::new(Length::Units(self.depth * INDENT_WIDTH), Length::Shrink))
+            .push(toggle)
+            .push(
     Button::new(&mut self.button_state, Text::new(&self.data.name)
             .vertical_alignment(VerticalAlignment::Center)
             .width(Length::Fill)
-            .horizontal_alignment(HorizontalAlignment::Center)
+            .horizontal_alignment(HorizontalAlignment::Left)
         ).padding(10).width(Length::Fill)
         .style(style::StyledButton::new(ButtonStylingType::Index(index)))
         .on_press(CategoryMessage::CategoryClicked(self.data.to_owned()))
+            )
+            .push({
+                let cat_id = self.data.id.to_owned();
+                Checkbox::new(self.archived, "", move |archived| CategoryMessage::ArchiveToggled(cat_id.clone(), archived))
+                .size(16)
+            })
+            .spacing(5)
+            .align_items(Align::Center)
         );
         container
-        .width(Length::FillPortion(1))
+        .width(Length::Fill)
         .center_y()
         .into()
     }
@@ -41,17 +75,45 @@ impl CategoryComponent {
 pub struct CategoriesComponent {
     scroll_state: scrollable::State,
     categories: Vec<CategoryComponent>,
+    expanded: HashSet<String>,
+    include_subfolders: bool,
+    // the disk space of the archived pages, as of the last StorageMeasured
+    archive_bytes: u64,
 }
 
 impl CategoriesComponent {
+    /// the categories come in pre-order, so every parent is seen before its subfolders
     pub fn new(categories: &mut Vec<BookmarkCategory>) -> Self {
+        let mut depths: HashMap<String, u16> = HashMap::new();
+        let parent_ids: HashSet<String> = categories
+            .iter()
+            .filter_map(|item| item.parent_id.clone())
+            .collect();
+        let archived_ids: HashSet<String> = {
+            let bookmark_api = BookmarkApi::init();
+            categories
+                .iter()
+                .filter(|item| bookmark_api.is_category_archived(&item.id))
+                .map(|item| item.id.clone())
+                .collect()
+        };
         CategoriesComponent {
             scroll_state: scrollable::State::new(),
             categories: Vec::from_iter(
                 categories
                     .iter()
-                    .map(|item| CategoryComponent::new(item.clone())),
+                    .map(|item| {
+                        let depth = item.parent_id
+                            .as_ref()
+                            .and_then(|parent_id| depths.get(parent_id))
+                            .map_or(0, |depth| depth + 1);
+                        depths.insert(item.id.clone(), depth);
+                        CategoryComponent::new(item.clone(), depth, parent_ids.contains(&item.id), archived_ids.contains(&item.id))
+                    }),
             ),
+            expanded: HashSet::new(),
+            include_subfolders: false,
+            archive_bytes: 0,
         }
     }
     pub fn update
@@ -65,7 +127,32 @@ impl CategoriesComponent {
      {
         match message {
             CategoryMessage::CategoryClicked(cat) => {
-                *state = State::LoadItems(0, Some(cat), None);
+                *state = State::LoadItems(0, MarksQuery {
+                    category: Some(cat),
+                    include_subfolders: self.include_subfolders,
+                    ..MarksQuery::default()
+                });
+                Command::none()
+            },
+            CategoryMessage::ToggleExpanded(cat_id) => {
+                if !self.expanded.remove(&cat_id) {
+                    self.expanded.insert(cat_id);
+                }
+                Command::none()
+            },
+            CategoryMessage::IncludeSubfoldersToggled(include_subfolders) => {
+                self.include_subfolders = include_subfolders;
+                Command::none()
+            },
+            CategoryMessage::ArchiveToggled(cat_id, archived) => {
+                BookmarkApi::init().set_category_archived(&cat_id, archived);
+                if let Some(component) = self.categories.iter_mut().find(|component| component.data.id == cat_id) {
+                    component.archived = archived;
+                }
+                Command::none()
+            },
+            CategoryMessage::StorageMeasured(archive_bytes) => {
+                self.archive_bytes = archive_bytes;
                 Command::none()
             },
             CategoryMessage::Reload(_) => {
@@ -73,7 +160,13 @@ impl CategoriesComponent {
                     let mut bookmark_api = BookmarkApi::init();
                     bookmark_api.get_categories().to_vec()
                 };
+                let expanded = self.expanded.clone();
+                let include_subfolders = self.include_subfolders;
+                let archive_bytes = self.archive_bytes;
                 *self = Self::new(&mut categories);
+                self.expanded = expanded;
+                self.include_subfolders = include_subfolders;
+                self.archive_bytes = archive_bytes;
                 Command::none()
             }
         }
@@ -89,43 +182,42 @@ impl CategoriesComponent {
             .align_items(Align::Center)
             .push(Space::new(Length::Fill, Length::Units(20)))
             .push(
-                Text::new("Select A Bookmark Category").size(18).color(style::TEXT_COLOR)
+                Text::new("Select A Bookmark Category").size(18).color(style::text_color())
             )
             .push(Rule::horizontal(30))
+            .push(
+                Checkbox::new(self.include_subfolders, "include subfolders", CategoryMessage::IncludeSubfoldersToggled)
+                .text_size(16)
+            )
+            .push(Space::new(Length::Fill, Length::Units(10)))
+            .push(
+                Text::new(format!("tick a folder to archive its pages, archives use {}", utils::format_bytes(self.archive_bytes)))
+                .size(14)
+                .color(style::secondary_color())
+            )
+            .push(Space::new(Length::Fill, Length::Units(10)))
         );
-        let mut row: Row<CategoryMessage> = Row::new()
-            .width(Length::Fill)
-            .spacing(5)
-            .padding(5)
-            .align_items(Align::Center);
-        let mut splicer = 0;
+        // a folder is shown when its parent is shown and expanded
+        let mut visible: HashSet<String> = HashSet::new();
         let mut index = 0;
-        let cat_length = self.categories.len();
-        let columns_count: u16 = 2;
         for component in self.categories.iter_mut() {
+            let is_visible = match &component.data.parent_id {
+                Some(parent_id) => visible.contains(parent_id) && self.expanded.contains(parent_id),
+                None => true,
+            };
+            if !is_visible {
+                continue;
+            }
+            visible.insert(component.data.id.clone());
             index += 1;
-            row = row.push(component.view(index)).align_items(Align::Center);
-            if splicer == columns_count - 1 {
-                all_content = all_content.push(row);
-                row = Row::new()
+            let expanded = self.expanded.contains(&component.data.id);
+            all_content = all_content.push(
+                Row::new()
                     .width(Length::Fill)
-                    .spacing(10)
-                    .padding(5)
-                    .align_items(Align::Center);
-                splicer = 0;
-            } else if index == cat_length {
-                all_content = all_content.push(row);
-                row = Row::new()
-                    .width(Length::Fill)
-                    .spacing(10)
-                    .padding(5)
-                    .align_items(Align::Center);
-                splicer = 0;
-            } else {
-                splicer += 1;
-            }
+                    .padding(2)
+                    .push(component.view(index, expanded))
+            );
         }
         all_content.into()
     }
 }
-
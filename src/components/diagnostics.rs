@@ -0,0 +1,124 @@
+use iced::{button, text_input, Button, Clipboard, Column, Command, Container, Element, Length, Row, Rule, Text, TextInput};
+use log::Level;
+
+use crate::config;
+use crate::services::logging::{self, LogEvent};
+use crate::style;
+
+use super::state::{DiagnosticsMessage, Message};
+
+// the panel gets slow past this, the log file has everything
+const MAX_SHOWN_EVENTS: usize = 300;
+
+/// the recent sync events, e.g. to find out why a bookmark has no image
+#[derive(Debug, Clone)]
+pub struct DiagnosticsComponent {
+    events: Vec<LogEvent>,
+    filter: String,
+    filter_input_state: text_input::State,
+    refresh_btn_state: button::State,
+    close_btn_state: button::State,
+}
+
+impl DiagnosticsComponent {
+    pub fn new() -> Self {
+        DiagnosticsComponent {
+            events: Vec::new(),
+            filter: String::new(),
+            filter_input_state: text_input::State::new(),
+            refresh_btn_state: button::State::new(),
+            close_btn_state: button::State::new(),
+        }
+    }
+    /// takes the events logged since the last time, the component is shown after this
+    pub fn refresh(&mut self) {
+        self.events = logging::recent_events();
+    }
+    /// a bookmark id, a url or any word of the message, case doesn't matter
+    fn matches(&self, event: &LogEvent) -> bool {
+        let filter = self.filter.trim().to_lowercase();
+        filter.is_empty()
+            || event.message.to_lowercase().contains(&filter)
+            || event.target.to_lowercase().contains(&filter)
+    }
+    pub fn update(&mut self, message: DiagnosticsMessage, _clipboard: &mut Clipboard) -> Command<Message> {
+        match message {
+            DiagnosticsMessage::FilterChanged(filter) => self.filter = filter,
+            DiagnosticsMessage::Refresh => self.refresh(),
+            DiagnosticsMessage::Close => (),
+        };
+        Command::none()
+    }
+    fn render_event(event: &LogEvent) -> Element<DiagnosticsMessage> {
+        let level_color = match event.level {
+            Level::Error | Level::Warn => style::PINK,
+            _ => style::secondary_color(),
+        };
+        Row::new()
+            .spacing(10)
+            .push(Text::new(&event.time).size(14).color(style::secondary_color()).width(Length::Units(100)))
+            .push(Text::new(event.level.to_string()).size(14).color(level_color).width(Length::Units(50)))
+            .push(Text::new(&event.message).size(14).color(style::text_color()).width(Length::Fill))
+            .into()
+    }
+    pub fn view(&mut self) -> Element<DiagnosticsMessage> {
+        let shown: Vec<&LogEvent> = self
+            .events
+            .iter()
+            .filter(|event| self.matches(event))
+            .take(MAX_SHOWN_EVENTS)
+            .collect();
+        let mut events = Column::new().spacing(6);
+        if shown.is_empty() {
+            events = events.push(Text::new("nothing was logged yet").size(16).color(style::secondary_color()));
+        }
+        for event in shown.into_iter() {
+            events = events.push(Self::render_event(event));
+        }
+        Container::new(
+            Column::new()
+            .spacing(15)
+            .push(
+                Row::new()
+                .spacing(10)
+                .push(Text::new("diagnostics").size(32).color(style::text_color()).width(Length::Fill))
+                .push(
+                    Button::new(&mut self.refresh_btn_state, Text::new("refresh"))
+                    .padding(10)
+                    .style(style::StyledButton::accent())
+                    .on_press(DiagnosticsMessage::Refresh)
+                )
+                .push(
+                    Button::new(&mut self.close_btn_state, Text::new("back to bookmarks"))
+                    .padding(10)
+                    .style(style::StyledButton::primary())
+                    .on_press(DiagnosticsMessage::Close)
+                )
+            )
+            .push(
+                Text::new(format!(
+                    "the newest events first, everything is kept in {}",
+                    config::get_log_file_path().display()
+                ))
+                .size(14)
+                .color(style::secondary_color())
+            )
+            .push(
+                TextInput::new(
+                    &mut self.filter_input_state,
+                    "filter by bookmark id, url or word",
+                    &self.filter,
+                    DiagnosticsMessage::FilterChanged
+                )
+                .padding(10)
+                .size(16)
+                .style(style::StyledTextInput::new())
+            )
+            .push(Rule::horizontal(10))
+            .push(events)
+        )
+        .width(Length::Fill)
+        .padding(30)
+        .into()
+    }
+}
@@ -1,20 +1,60 @@
 
-use super::state::{State, HeaderMessage, Message, CategoryMessage, SideBarMessage, MCMessage};
-use iced::{Button, button};
+use super::state::{State, HeaderMessage, Message, CategoryMessage, SideBarMessage, MCMessage, SourceMessage};
+use super::sources::SourceOption;
+use iced::{Button, button, PickList, pick_list};
 use iced::{
-    Column, Element, Clipboard, Command, Text, Container, Length, Row, VerticalAlignment, Svg, svg, Align
+    Column, Element, Clipboard, Command, Text, Container, Length, Row, VerticalAlignment, Svg, svg, Align, ProgressBar
 };
 
 use crate::services::asset::Asset;
 use crate::services::bookmark_api::{BookmarkApi};
+use crate::services::sync::SyncProgress;
 use crate::style;
 use crate::config;
+use crate::utils::{self, Error};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+// how long a toast stays up
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+// the oldest errors are dropped from the log past this
+const MAX_LOGGED_ERRORS: usize = 200;
+
+#[derive(Debug, Clone)]
+struct LoggedError {
+    /// local time, HH:MM:SS
+    time: String,
+    error: Error,
+}
+
 #[derive(Debug, Clone)]
 pub struct Header {
     loading: bool,
     clear_cache_btn_state: button::State,
     resync_btn_state: button::State,
-    icon_handle: svg::Handle
+    import_btn_state: button::State,
+    export_btn_state: button::State,
+    health_btn_state: button::State,
+    diagnostics_btn_state: button::State,
+    profile_picker_state: pick_list::State<SourceOption>,
+    profiles: Vec<SourceOption>,
+    selected_profile: SourceOption,
+    watch_paths: Vec<PathBuf>,
+    // a sync was asked for while another one was running
+    pending_sync: bool,
+    // counts the syncs, the running one is streamed by the subscription with its number
+    sync_runs: u64,
+    sync_running: bool,
+    sync_progress: Option<SyncProgress>,
+    icon_handle: svg::Handle,
+    // newest first
+    errors: VecDeque<LoggedError>,
+    toast: Option<(String, Instant)>,
+    show_error_log: bool,
+    error_log_btn_state: button::State,
+    dismiss_toast_btn_state: button::State,
+    clear_errors_btn_state: button::State
 }
 
 impl Header {
@@ -29,14 +69,49 @@ impl Header {
             // println!("+=================== asset is {:#?}", asset);
             // if asset.is_som
         };
+        let (profiles, selected_profile) = Self::load_profiles();
+        let watch_paths = BookmarkApi::init().get_watch_paths();
         Header {
             loading: false,
             clear_cache_btn_state: button::State::new(),
             resync_btn_state: button::State::new(),
-            icon_handle: asset_handler
+            import_btn_state: button::State::new(),
+            export_btn_state: button::State::new(),
+            health_btn_state: button::State::new(),
+            diagnostics_btn_state: button::State::new(),
+            profile_picker_state: pick_list::State::default(),
+            profiles,
+            selected_profile,
+            watch_paths,
+            pending_sync: false,
+            sync_runs: 0,
+            sync_running: false,
+            sync_progress: None,
+            icon_handle: asset_handler,
+            errors: VecDeque::new(),
+            toast: None,
+            show_error_log: false,
+            error_log_btn_state: button::State::new(),
+            dismiss_toast_btn_state: button::State::new(),
+            clear_errors_btn_state: button::State::new()
             //  svg::Handle::from_memory(Asset::get(config::get_loader_icon_path().to_str().unwrap()).unwrap().data.to_vec())
         }
     }
+    fn load_profiles() -> (Vec<SourceOption>, SourceOption) {
+        let bookmark_api = BookmarkApi::init();
+        let mut profiles = vec![SourceOption::All];
+        profiles.extend(
+            bookmark_api
+                .get_sources()
+                .into_iter()
+                .map(|(info, _)| SourceOption::Source(info)),
+        );
+        let selected = match bookmark_api.get_active_profile() {
+            Some(info) => SourceOption::Source(info),
+            None => SourceOption::All,
+        };
+        (profiles, selected)
+    }
     pub fn update(
         &mut self,
         message: HeaderMessage,
@@ -44,34 +119,163 @@ impl Header {
         _state: &mut State
     ) -> Command<Message> {
             match message{
+                // the sync itself runs in the subscription main starts for running_sync
                 HeaderMessage::Loading => {
                     self.loading = true;
+                    self.sync_runs += 1;
+                    self.sync_running = true;
+                    self.sync_progress = None;
                     Command::batch([
-                        Command::perform(BookmarkApi::sync_all(), Message::Synced),
                         Command::perform(BookmarkApi::perform_load(), CategoryMessage::Reload)
                         .map(|m| Message::SideBarMessage(SideBarMessage::CategoryMessage(m))),
+                        Command::perform(BookmarkApi::perform_load(), SourceMessage::Reload)
+                        .map(|m| Message::SideBarMessage(SideBarMessage::SourceMessage(m))),
                         Command::perform(BookmarkApi::perform_load(), MCMessage::Refresh)
                         .map(|m| Message::MCEvent(m))
                     ])
                 },
                 HeaderMessage::Loaded => {
                     self.loading = false;
+                    self.sync_running = false;
+                    self.sync_progress = None;
+                    let (profiles, selected_profile) = Self::load_profiles();
+                    self.profiles = profiles;
+                    self.selected_profile = selected_profile;
+                    self.watch_paths = BookmarkApi::init().get_watch_paths();
+                    if self.pending_sync {
+                        self.pending_sync = false;
+                        self.loading = true;
+                        return Command::perform(BookmarkApi::perform_load(), Message::Syncing);
+                    }
+                    Command::none()
+                },
+                HeaderMessage::BookmarksChanged => self.request_sync(),
+                HeaderMessage::Import => {
+                    Command::perform(BookmarkApi::import_html(), Message::Syncing)
+                },
+                HeaderMessage::Export => {
+                    Command::perform(BookmarkApi::export_html(), HeaderMessage::Exported)
+                    .map(|m| Message::HeaderMessage(m))
+                },
+                HeaderMessage::Exported(result) => {
+                    if let Err(error) = result {
+                        self.report(vec![error]);
+                    }
+                    Command::none()
+                },
+                HeaderMessage::Tick(now) => {
+                    if self.toast.as_ref().map_or(false, |(_, shown)| now.duration_since(*shown) >= TOAST_DURATION) {
+                        self.toast = None;
+                    }
+                    Command::none()
+                },
+                HeaderMessage::SyncProgress(progress) => {
+                    if self.sync_running {
+                        self.sync_progress = Some(progress);
+                    }
+                    Command::none()
+                },
+                HeaderMessage::DismissToast => {
+                    self.toast = None;
+                    Command::none()
+                },
+                HeaderMessage::ToggleErrorLog => {
+                    self.show_error_log = !self.show_error_log;
+                    self.toast = None;
                     Command::none()
                 },
+                HeaderMessage::ClearErrors => {
+                    self.errors.clear();
+                    Command::none()
+                },
+                HeaderMessage::ProfileSelected(option) => {
+                    {
+                        let mut bookmark_api = BookmarkApi::init();
+                        bookmark_api.set_active_profile(match &option {
+                            SourceOption::All => None,
+                            SourceOption::Source(info) => Some(info.id.clone()),
+                        });
+                    }
+                    self.selected_profile = option;
+                    self.request_sync()
+                },
                 HeaderMessage::ClearCache => {
                     Command::perform(BookmarkApi::flush_all_resync(), Message::Syncing)
                 },
-                HeaderMessage::Resync => {
-                    self.loading = true;
-                    Command::perform(BookmarkApi::perform_load(), Message::Syncing)
-                }
+                // the main view swaps the marks for the report or the diagnostics
+                HeaderMessage::ShowHealth | HeaderMessage::ShowDiagnostics => {
+                    Command::none()
+                },
+                HeaderMessage::Resync => self.request_sync()
             }
     }
+    /// starts a sync, or queues one for when the running sync is done, starting it
+    /// right away would drop the running one
+    pub fn request_sync(&mut self) -> Command<Message> {
+        if self.loading {
+            self.pending_sync = true;
+            return Command::none();
+        }
+        self.loading = true;
+        Command::perform(BookmarkApi::perform_load(), Message::Syncing)
+    }
+    /// logs the errors and shows a toast about them
+    pub fn report(&mut self, errors: Vec<Error>) {
+        let toast = match errors.len() {
+            0 => return,
+            1 => errors[0].to_string(),
+            count => format!("{} things went wrong, see the error log", count),
+        };
+        let time = chrono::Local::now().format("%H:%M:%S").to_string();
+        for error in errors.into_iter() {
+            self.errors.push_front(LoggedError { time: time.clone(), error });
+        }
+        self.errors.truncate(MAX_LOGGED_ERRORS);
+        self.toast = Some((toast, Instant::now()));
+    }
+    /// the number of the sync to stream, None when no sync is running
+    pub fn running_sync(&self) -> Option<u64> {
+        if self.sync_running {
+            Some(self.sync_runs)
+        } else {
+            None
+        }
+    }
+    /// "scraping 12 of 40, 2 failed, about 1 min left" with a bar and the url being worked on
+    fn render_progress<'a>(progress: &SyncProgress) -> Element<'a, HeaderMessage> {
+        let mut summary = format!("{} {} of {}", progress.stage, progress.done, progress.total);
+        if progress.failed > 0 {
+            summary.push_str(&format!(", {} failed", progress.failed));
+        }
+        if let Some(eta) = progress.eta() {
+            summary.push_str(&format!(", {} left", utils::format_eta(eta)));
+        }
+        let mut content = Column::new()
+            .spacing(4)
+            .push(Text::new(summary).size(16).color(style::text_color()))
+            .push(
+                ProgressBar::new(0.0..=progress.total.max(1) as f32, progress.done as f32)
+                .height(Length::Units(8))
+                .style(style::SyncProgressBar)
+            );
+        if let Some(url) = &progress.current_url {
+            content = content.push(Text::new(utils::truncate_with_dots(url, 60)).size(12).color(style::secondary_color()));
+        }
+        content.into()
+    }
+    /// the toast needs ticks to go away on its own
+    pub fn toast_visible(&self) -> bool {
+        self.toast.is_some()
+    }
+    /// the bookmark files the live sync watches
+    pub fn watch_paths(&self) -> Vec<PathBuf> {
+        self.watch_paths.clone()
+    }
     pub fn view(&mut self) -> Element<HeaderMessage> {
         let mut content: Row<HeaderMessage> = Row::new().height(Length::Units(60));
         content = content.push(
             Column::new().push(
-                Text::new("Roboza Press").color(style::TEXT_COLOR).size(25).height(Length::Fill).vertical_alignment(VerticalAlignment::Center)
+                Text::new("Roboza Press").color(style::text_color()).size(25).height(Length::Fill).vertical_alignment(VerticalAlignment::Center)
             )
             .width(Length::FillPortion(2)).height(Length::Fill)
             // .padding(20)
@@ -79,10 +283,15 @@ impl Header {
         .padding(10)
         .width(Length::Fill);
         if self.loading {
+            let status: Element<HeaderMessage> = match &self.sync_progress {
+                Some(progress) => Self::render_progress(progress),
+                None => Text::new("syncing and loading marks...")
+                    .color(style::text_color())
+                    .vertical_alignment(VerticalAlignment::Center).height(Length::Fill)
+                    .into(),
+            };
             content = content.push(
-                Text::new("syncing and loading marks...")
-                .color(style::TEXT_COLOR).width(Length::FillPortion(1))
-                .vertical_alignment(VerticalAlignment::Center).height(Length::Fill)
+                Container::new(status).width(Length::FillPortion(1)).height(Length::Fill).center_y()
             )
             .push(
                 Svg::new(
@@ -108,14 +317,97 @@ impl Header {
                         .padding(10)
                         .on_press(HeaderMessage::Resync)
                     )
+                    .push(
+                        Button::new(&mut self.import_btn_state, Text::new("import html"))
+                        .style(style::StyledButton::primary())
+                        .padding(10)
+                        .on_press(HeaderMessage::Import)
+                    )
+                    .push(
+                        Button::new(&mut self.export_btn_state, Text::new("export html"))
+                        .style(style::StyledButton::primary())
+                        .padding(10)
+                        .on_press(HeaderMessage::Export)
+                    )
+                    .push(
+                        Button::new(&mut self.health_btn_state, Text::new("link health"))
+                        .style(style::StyledButton::primary())
+                        .padding(10)
+                        .on_press(HeaderMessage::ShowHealth)
+                    )
+                    .push(
+                        Button::new(&mut self.diagnostics_btn_state, Text::new("diagnostics"))
+                        .style(style::StyledButton::primary())
+                        .padding(10)
+                        .on_press(HeaderMessage::ShowDiagnostics)
+                    )
+                    .push(
+                        PickList::new(
+                            &mut self.profile_picker_state,
+                            self.profiles.clone(),
+                            Some(self.selected_profile.clone()),
+                            HeaderMessage::ProfileSelected
+                        )
+                        .padding(10)
+                    )
                     .width(Length::Fill)
                     .spacing(10)
                 )
-                .width(Length::FillPortion(1))
+                .width(Length::FillPortion(3))
             );
             // .align_items(A)
         }
-        Container::new(content)
+        content = content.push(
+            Button::new(&mut self.error_log_btn_state, Text::new(format!("errors ({})", self.errors.len())))
+            .style(style::StyledButton::primary())
+            .padding(10)
+            .on_press(HeaderMessage::ToggleErrorLog)
+        );
+        let mut all_content: Column<HeaderMessage> = Column::new().push(content);
+        if let Some((toast, _)) = &self.toast {
+            all_content = all_content.push(
+                Row::new()
+                .padding(10)
+                .spacing(10)
+                .align_items(Align::Center)
+                .push(Text::new(toast).size(16).color(style::PINK).width(Length::Fill))
+                .push(
+                    Button::new(&mut self.dismiss_toast_btn_state, Text::new("dismiss"))
+                    .style(style::StyledButton::primary())
+                    .padding(5)
+                    .on_press(HeaderMessage::DismissToast)
+                )
+            );
+        }
+        if self.show_error_log {
+            let mut log = Column::new()
+                .padding(10)
+                .spacing(5)
+                .push(
+                    Row::new()
+                    .spacing(10)
+                    .align_items(Align::Center)
+                    .push(Text::new("error log").size(18).color(style::text_color()).width(Length::Fill))
+                    .push(
+                        Button::new(&mut self.clear_errors_btn_state, Text::new("clear"))
+                        .style(style::StyledButton::primary())
+                        .padding(5)
+                        .on_press(HeaderMessage::ClearErrors)
+                    )
+                );
+            if self.errors.is_empty() {
+                log = log.push(Text::new("nothing went wrong so far").size(14).color(style::secondary_color()));
+            }
+            for logged in self.errors.iter() {
+                log = log.push(
+                    Text::new(format!("{}  {}", logged.time, logged.error))
+                    .size(14)
+                    .color(style::text_color())
+                );
+            }
+            all_content = all_content.push(log);
+        }
+        Container::new(all_content)
             .width(Length::Fill)
             .style(style::Header)
             // .push()
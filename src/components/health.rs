@@ -0,0 +1,144 @@
+use iced::{button, Button, Clipboard, Column, Command, Container, Element, Length, Row, Rule, Text};
+
+use crate::services::bookmark_api::BookmarkApi;
+use crate::services::health::{HealthEntry, HealthReport};
+use crate::style;
+
+use super::state::{HealthMessage, Message};
+
+#[derive(Debug, Clone)]
+pub struct HealthComponent {
+    report: Option<HealthReport>,
+    // a bulk fix is being saved
+    saving: bool,
+    error: Option<String>,
+    close_btn_state: button::State,
+    update_btn_state: button::State,
+    remove_btn_state: button::State,
+}
+
+impl HealthComponent {
+    pub fn new() -> Self {
+        HealthComponent {
+            report: None,
+            saving: false,
+            error: None,
+            close_btn_state: button::State::new(),
+            update_btn_state: button::State::new(),
+            remove_btn_state: button::State::new(),
+        }
+    }
+    /// loads the report again, the component is shown after this
+    pub fn open(&mut self) -> Command<Message> {
+        self.report = None;
+        self.error = None;
+        Command::perform(BookmarkApi::load_health_report(), HealthMessage::Loaded)
+            .map(Message::HealthMessage)
+    }
+    pub fn update(&mut self, message: HealthMessage, _clipboard: &mut Clipboard) -> Command<Message> {
+        match message {
+            HealthMessage::Loaded(result) => {
+                match result {
+                    Ok(report) => self.report = Some(report),
+                    Err(_) => self.error = Some(String::from("the report couldn't be loaded from the library")),
+                };
+                Command::none()
+            },
+            HealthMessage::UpdateRedirects => {
+                self.saving = true;
+                Command::perform(BookmarkApi::update_redirected_links(), HealthMessage::FixesSaved)
+                    .map(Message::HealthMessage)
+            },
+            HealthMessage::RemoveDead => {
+                self.saving = true;
+                Command::perform(BookmarkApi::remove_dead_bookmarks(), HealthMessage::FixesSaved)
+                    .map(Message::HealthMessage)
+            },
+            // the main view resyncs so the fixes show up in the library
+            HealthMessage::FixesSaved(result) => {
+                self.saving = false;
+                let command = self.open();
+                if result.is_err() {
+                    self.error = Some(String::from("the fixes couldn't be saved"));
+                }
+                command
+            },
+            HealthMessage::Close => Command::none(),
+        }
+    }
+    fn render_section<'a>(title: &str, entries: &'a [HealthEntry]) -> Element<'a, HealthMessage> {
+        let mut section = Column::new()
+            .spacing(8)
+            .push(Text::new(format!("{} ({})", title, entries.len())).size(22).color(style::text_color()));
+        for entry in entries.iter() {
+            section = section.push(
+                Column::new()
+                .spacing(2)
+                .push(Text::new(&entry.title).size(16).color(style::text_color()))
+                .push(Text::new(&entry.link).size(14).color(style::secondary_color()))
+                .push(Text::new(&entry.detail).size(14).color(style::secondary_color()))
+            );
+        }
+        section.into()
+    }
+    pub fn view(&mut self) -> Element<HealthMessage> {
+        let mut content = Column::new()
+            .spacing(15)
+            .push(
+                Row::new()
+                .push(Text::new("link health").size(32).color(style::text_color()).width(Length::Fill))
+                .push(
+                    Button::new(&mut self.close_btn_state, Text::new("back to bookmarks"))
+                    .padding(10)
+                    .style(style::StyledButton::primary())
+                    .on_press(HealthMessage::Close)
+                )
+            );
+        if let Some(error) = &self.error {
+            content = content.push(Text::new(error).size(16).color(style::secondary_color()));
+        }
+        let report = match &self.report {
+            Some(report) => report,
+            None => {
+                content = content.push(Text::new("checking the report...").size(18).color(style::text_color()));
+                return Container::new(content).width(Length::Fill).padding(30).into();
+            }
+        };
+        content = content.push(
+            Text::new(format!(
+                "{} links checked, {} not checked yet, links are checked once a day while syncing",
+                report.checked, report.unchecked
+            ))
+            .size(16)
+            .color(style::secondary_color())
+        );
+        let redirected = report.redirected.len();
+        let dead = report.dead().len();
+        let mut actions = Row::new().spacing(10);
+        let mut update_btn = Button::new(&mut self.update_btn_state, Text::new(format!("update {} redirected urls", redirected)))
+            .padding(10)
+            .style(style::StyledButton::accent());
+        if redirected > 0 && !self.saving {
+            update_btn = update_btn.on_press(HealthMessage::UpdateRedirects);
+        }
+        let mut remove_btn = Button::new(&mut self.remove_btn_state, Text::new(format!("remove {} dead bookmarks", dead)))
+            .padding(10)
+            .style(style::StyledButton::primary());
+        if dead > 0 && !self.saving {
+            remove_btn = remove_btn.on_press(HealthMessage::RemoveDead);
+        }
+        actions = actions.push(update_btn).push(remove_btn);
+        content = content
+            .push(actions)
+            .push(Rule::horizontal(10))
+            .push(Self::render_section("not found", &report.not_found))
+            .push(Self::render_section("gone domains", &report.gone_domains))
+            .push(Self::render_section("moved permanently", &report.redirected))
+            .push(Self::render_section("failing", &report.failing))
+            .push(Self::render_section("slow", &report.slow));
+        Container::new(content)
+            .width(Length::Fill)
+            .padding(30)
+            .into()
+    }
+}
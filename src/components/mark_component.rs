@@ -7,48 +7,92 @@
 
 
 use iced::{
-    Align, Clipboard, Column, Command, Container, Element, Image, Length, Row, Text, Rule, HorizontalAlignment, Space, image, Button, button, TextInput, text_input
+    Align, Clipboard, Column, Command, Container, Element, Image, Length, Row, Text, Rule, HorizontalAlignment, Space, image, Button, button, TextInput, text_input, PickList, pick_list, Checkbox, Svg, svg
 };
 use super::state::{MCMessage, State, Events};
-use crate::{style, services::{ bookmark_api::{MarkData, BookmarkApi, BookmarkCategory}, asset::Asset}, config, utils};
+use crate::{style, services::{ bookmark_api::{BookmarkApi, MarkData, MarksQuery, PageCursor, SortOrder}, mark_store::{SNIPPET_END, SNIPPET_START}, query, asset::Asset, source::SourceInfo}, config, user_config, utils};
 use open;
 
+// how many characters of a search snippet fit on one line of a card
+const SNIPPET_LINE_CHARS: usize = 45;
+const DESCRIPTION_MAX_CHARS: usize = 120;
+const CARD_HEIGHT: u16 = 460;
+// the next page is loaded once the end of the list is less than this many screens away
+const LOAD_AHEAD_SCREENS: f32 = 1.0;
+
 #[derive(Clone, Debug)]
 pub struct MarkComponent {
     pub data: MarkData,
     button_state: button::State,
+    read_button_state: button::State,
+    archive_button_state: button::State,
 }
 
 impl MarkComponent {
     pub fn new(data: MarkData) -> Self {
-        MarkComponent { data, button_state: button::State::new() }
+        MarkComponent {
+            data,
+            button_state: button::State::new(),
+            read_button_state: button::State::new(),
+            archive_button_state: button::State::new(),
+        }
+    }
+    /// lays the snippet out word by word, iced text can't change color midway
+    fn render_snippet<'a>(snippet: &str) -> Column<'a, MCMessage> {
+        let mut words: Vec<(String, bool)> = Vec::new();
+        for (i, part) in snippet.split(SNIPPET_START).enumerate() {
+            let (matched, rest) = match part.split_once(SNIPPET_END) {
+                Some((matched, rest)) if i > 0 => (matched, rest),
+                _ => ("", part),
+            };
+            words.extend(matched.split_whitespace().map(|word| (word.to_string(), true)));
+            words.extend(rest.split_whitespace().map(|word| (word.to_string(), false)));
+        }
+        let mut lines = Column::new().spacing(2).align_items(Align::Center);
+        let mut line: Row<MCMessage> = Row::new().spacing(4);
+        let mut line_chars: usize = 0;
+        for (word, matched) in words.into_iter() {
+            if line_chars > 0 && line_chars + word.chars().count() > SNIPPET_LINE_CHARS {
+                lines = lines.push(line);
+                line = Row::new().spacing(4);
+                line_chars = 0;
+            }
+            line_chars += word.chars().count() + 1;
+            line = line.push(
+                Text::new(word).size(15).color(if matched { style::secondary_color() } else { style::text_color() })
+            );
+        }
+        if line_chars > 0 {
+            lines = lines.push(line);
+        }
+        lines
     }
     pub fn view(&mut self) -> Element<MCMessage> {
-        let image_handler: image::Handle = match &self.data.image.as_str() {
+        // the image widget can't draw vector images, the svg one can
+        let preview: Element<MCMessage> = match &self.data.image.as_str() {
             &config::DEFAULT_IMG_PATH => {
                 let img_path = config::get_default_image_path();
                 let re = Asset::get(img_path.to_str().unwrap()).unwrap().data;
-                image::Handle::from_memory(re.to_vec())
+                Image::new(image::Handle::from_memory(re.to_vec())).width(Length::Fill).height(Length::Fill).into()
             },
             _ => {
                 let split_path: Vec<&str> = self.data.image.split("?").collect();
                 let img_path = config::get_full_img_cache_path().join(split_path[0]);
-                image::Handle::from_path(img_path)
+                if split_path[0].ends_with(".svg") {
+                    Svg::new(svg::Handle::from_path(img_path)).width(Length::Fill).height(Length::Fill).into()
+                } else {
+                    Image::new(image::Handle::from_path(img_path)).width(Length::Fill).height(Length::Fill).into()
+                }
             }
         };
         let max_chars: usize = 60;
         let title = utils::truncate_with_dots(&mut self.data.title, max_chars);
+        let description = utils::truncate_with_dots(&self.data.description, DESCRIPTION_MAX_CHARS);
         Container::new(
             Column::new()
                 .push(
                     Column::new()
-                    .push(
-                        Image::new(
-                            image_handler
-                        )
-                        .width(Length::Fill)
-                        .height(Length::Fill)
-                    )
+                    .push(preview)
                     .width(Length::Fill)
                     .height(Length::Units(210))
                     .align_items(Align::Center)
@@ -57,13 +101,44 @@ impl MarkComponent {
                 .push(
                     Column::new()
                     .push(Text::new(title))
-                    // .push(Text::new(&self.data.description))
+                    // a search shows where it matched instead of the description
+                    .push(match &self.data.snippet {
+                        Some(snippet) => Self::render_snippet(snippet).padding(10),
+                        None if !description.is_empty() => Column::new()
+                            .push(Text::new(description).size(15).color(style::text_color()))
+                            .padding(10),
+                        None => Column::new(),
+                    })
+                    .push(Space::new(Length::Fill, Length::Units(10)))
+                    .push(
+                        Text::new(match self.data.date_added {
+                            Some(date) => format!("added {}", utils::format_date(date)),
+                            None => String::new(),
+                        })
+                        .size(16)
+                        .color(style::secondary_color())
+                    )
                     .align_items(Align::Center)
                 )
                 .push(
                     Column::new()
                     .push(
-                    Button::new(
+                        Row::new()
+                        .spacing(10)
+                        .push(
+                            Button::new(
+                                &mut self.read_button_state,
+                                Text::new("Read")
+                                .width(Length::Fill)
+                                .horizontal_alignment(HorizontalAlignment::Center)
+                            )
+                            .width(Length::Units(100))
+                            .padding(10)
+                            .style(style::StyledButton::primary())
+                            .on_press(MCMessage::ReadClicked(self.data.clone()))
+                        )
+                        .push(
+                        Button::new(
                             &mut self.button_state,
                     Text::new("Go to")
                             .width(Length::Fill)
@@ -73,11 +148,24 @@ impl MarkComponent {
                         .padding(10)
                         .style(style::StyledButton::accent())
                         .on_press(MCMessage::GotoClicked(self.data.clone()))
+                        )
                     )
+                    .push(match &self.data.archive {
+                        Some(copy) => Column::new().push(Space::new(Length::Fill, Length::Units(10))).push(
+                            Button::new(
+                                &mut self.archive_button_state,
+                                Text::new(format!("archived copy ({})", utils::format_bytes(copy.size)))
+                            )
+                            .padding(10)
+                            .style(style::StyledButton::primary())
+                            .on_press(MCMessage::OpenArchiveClicked(self.data.clone()))
+                        ),
+                        None => Column::new(),
+                    })
                     .push(Space::new(Length::Fill, Length::Units(20)))
                     .push(
-                        Text::new(&self.data.category.name)
-                        .color(style::SECONDARY_COLOR)
+                        Text::new(self.data.category.display_path())
+                        .color(style::secondary_color())
                 )
                     .align_items(Align::End)
                     .padding(30)
@@ -90,7 +178,7 @@ impl MarkComponent {
         .align_y(Align::Center)
         .style(style::FeedCard)
         .width(Length::Fill)
-        .height(Length::Units(460))
+        .height(Length::Units(CARD_HEIGHT))
         .into()
     }
 }
@@ -100,10 +188,33 @@ pub struct MarkComponents {
     items: Vec<MarkComponent>,
     current_cat_id: Option<String>,
     load_more_btn_state: button::State,
+    // the mark open in the reader, the list is hidden meanwhile
+    reading: Option<MarkData>,
+    reader_back_btn_state: button::State,
+    reader_goto_btn_state: button::State,
     search_input_state: text_input::State,
     search_input_val: String,
+    source_filter: Option<SourceInfo>,
+    current_query: Option<MarksQuery>,
+    sort_state: pick_list::State<SortOrder>,
+    sort: SortOrder,
+    page_size_state: pick_list::State<usize>,
+    page_size: usize,
+    added_from_state: text_input::State,
+    added_from_val: String,
+    added_to_state: text_input::State,
+    added_to_val: String,
+    date_error: Option<String>,
+    search_error: Option<String>,
+    regex_mode: bool,
     last_index: usize,
-    has_more: bool
+    has_more: bool,
+    next_page: Option<PageCursor>,
+    total: usize,
+    loading_more: bool,
+    // how many marks the reload after a sync brings back, the cards on screen are replaced
+    // in place meanwhile so the list keeps its height and scroll position
+    resync_len: Option<usize>
 }
 
 impl MarkComponents {
@@ -113,10 +224,30 @@ impl MarkComponents {
             items: Self::render_items(&all_items),
             current_cat_id: None,
             load_more_btn_state: button::State::new(),
+            reading: None,
+            reader_back_btn_state: button::State::new(),
+            reader_goto_btn_state: button::State::new(),
             search_input_state: text_input::State::new(),
             search_input_val: String::new(),
+            source_filter: None,
+            current_query: None,
+            sort_state: pick_list::State::default(),
+            sort: SortOrder::default(),
+            page_size_state: pick_list::State::default(),
+            page_size: user_config::get().page_size,
+            added_from_state: text_input::State::new(),
+            added_from_val: String::new(),
+            added_to_state: text_input::State::new(),
+            added_to_val: String::new(),
+            date_error: None,
+            search_error: None,
+            regex_mode: false,
             last_index: 0,
-            has_more: false
+            has_more: false,
+            next_page: None,
+            total: 0,
+            loading_more: false,
+            resync_len: None
         }
     }
     fn render_items(marks: &Vec<MarkData>) -> Vec<MarkComponent> {
@@ -138,95 +269,345 @@ impl MarkComponents {
                 *state = State::None;
             }
             MCMessage::LoadMark(mark_meta) => {
-                match &mark_meta.mark {
-                    Some(mark) => {
-                        if self.search_input_val.is_empty() {
-                            match &self.current_cat_id {
-                                Some(current_cat_id) => {
-                                    if current_cat_id != &mark.category.id {
-                                        self.items = Vec::new();
-                                        self.current_cat_id = Some(mark.category.id.to_string());
-                                        self.search_input_val = String::new();
-                                    }
-                                },
-                                None => {
-                                    self.current_cat_id = Some(mark.category.id.to_string());
-                                },
-                            };
+                match (&mark_meta.mark, self.resync_len) {
+                    (Some(mark), Some(resync_len)) => {
+                        let index = mark_meta.index;
+                        match self.items.get_mut(index) {
+                            Some(item) => *item = MarkComponent::new(mark.clone()),
+                            None => self.items.push(MarkComponent::new(mark.clone())),
                         }
+                        self.last_index = index;
+                        let expected = if mark_meta.has_more { resync_len } else { mark_meta.all_len };
+                        if index + 1 >= expected {
+                            self.items.truncate(index + 1);
+                            self.resync_len = None;
+                        }
+                    },
+                    (Some(mark), None) => {
                         if mark_meta.index == 0 {
                             self.items = Vec::new();
                         }
                         self.items.push(MarkComponent::new(mark.clone()));
                         self.last_index = mark_meta.index;
                     },
-                    None => {
+                    (None, _) => {
                         self.items = Vec::new();
                         self.last_index = 0;
-                        self.current_cat_id = None;
-                        *state = State::None;
+                        self.resync_len = None;
                     }
                 }
+                self.current_cat_id = mark_meta.query.category.as_ref().map(|cat| cat.id.to_string());
+                // the next pages have the picked size again, not the size of the reload
+                self.current_query = Some(MarksQuery {
+                    page_size: Some(self.page_size),
+                    ..mark_meta.query.clone()
+                });
                 self.has_more = mark_meta.has_more;
+                self.next_page = mark_meta.next.clone();
+                self.total = mark_meta.all_len;
+                self.loading_more = false;
                 *state = State::None;
             },
             MCMessage::GotoClicked(mark) => {
                 open::that(&mark.link).ok();
             },
+            MCMessage::OpenArchiveClicked(mark) => {
+                if let Some(copy) = &mark.archive {
+                    open::that(copy.path()).ok();
+                }
+            },
+            MCMessage::ReadClicked(mark) => {
+                self.reading = Some(mark.clone());
+            },
+            MCMessage::CloseReader => {
+                self.reading = None;
+            },
             MCMessage::LoadMore(i) => {
-                let index = &(i + 1);
-                if self.current_cat_id.is_some() && self.search_input_val.is_empty() {
-                    let mut bookmark_api = BookmarkApi::init();
-                    let cat = bookmark_api.get_category(&self.current_cat_id.as_ref().unwrap());
-                    if cat.is_some() {
-                        *state = State::LoadItems(*index, Some(cat.unwrap().clone()), None);
-                    } else {
-                        *state = State::None;
-                    }
-                } else if self.current_cat_id.is_none() && !self.search_input_val.is_empty() {
-                    *state = State::LoadItems(*index, None, Some(self.search_input_val.to_string()));
-                } else {
-                    *state = State::None;
+                self.load_more(*i, state);
+            },
+            MCMessage::Scrolled(_, _, _) if self.reading.is_some() => (),
+            MCMessage::Scrolled(offset, viewport_height, content_height) => {
+                let left_below = content_height - (offset + viewport_height);
+                if left_below <= viewport_height * LOAD_AHEAD_SCREENS {
+                    self.load_more(self.last_index, state);
                 }
             },
             MCMessage::SearchInputChanged(val) => {
                 self.search_input_val = val.to_string();
+                self.search_error = None;
+            },
+            MCMessage::RegexToggled(regex_mode) => {
+                self.regex_mode = *regex_mode;
+                self.search_error = None;
             },
             MCMessage::Search => {
-                self.last_index = 0;
-                self.current_cat_id = None;
-                if !self.search_input_val.is_empty() {
-                    self.items = Vec::new();
-                    *state = State::LoadItems(self.last_index, None, Some(self.search_input_val.to_string()));
+                let checked = if self.regex_mode {
+                    query::parse_regex(&self.search_input_val).map(|_| ())
                 } else {
-                    *state = State::LoadItems(self.last_index, Some(BookmarkCategory::default()), None);
+                    query::parse(&self.search_input_val).map(|_| ())
+                };
+                if let Err(error) = checked {
+                    self.search_error = Some(error.to_string());
+                    return Command::none();
                 }
+                self.last_index = 0;
+                self.current_cat_id = None;
+                self.items = Vec::new();
+                self.resync_len = None;
+                *state = State::LoadItems(self.last_index, self.search_query());
             },
             MCMessage::CategoryClicked => {
+                self.reading = None;
                 self.current_cat_id = None;
                 self.search_input_val = String::new();
+                self.search_error = None;
+                self.source_filter = None;
+                self.resync_len = None;
+            },
+            MCMessage::SourceSelected(source) => {
+                self.source_filter = source.clone();
+                self.last_index = 0;
+                self.current_cat_id = None;
+                self.items = Vec::new();
+                self.resync_len = None;
+                *state = State::LoadItems(0, self.search_query());
+            },
+            MCMessage::SortSelected(sort) => {
+                self.sort = *sort;
+                self.reload(state);
+            },
+            MCMessage::PageSizeSelected(page_size) => {
+                self.page_size = *page_size;
+                self.reload(state);
+            },
+            MCMessage::AddedFromChanged(val) => {
+                self.added_from_val = val.to_string();
+            },
+            MCMessage::AddedToChanged(val) => {
+                self.added_to_val = val.to_string();
+            },
+            MCMessage::MarkSynced(mark, new) => {
+                self.show_synced(mark, *new);
+            },
+            MCMessage::ApplyDateFilter => {
+                self.reload(state);
             }
         }
+        self.apply_view_options(state);
         Command::none()
     }
+    pub fn last_index(&self) -> usize {
+        self.last_index
+    }
+    /// the next page is loaded with the same query that produced the current one
+    fn load_more(&mut self, last_index: usize, state: &mut State) {
+        if self.loading_more || self.resync_len.is_some() || self.items.is_empty() {
+            return;
+        }
+        if let (Some(query), Some(next_page)) = (&self.current_query, &self.next_page) {
+            *state = State::LoadItems(last_index + 1, MarksQuery {
+                after: Some(next_page.clone()),
+                ..query.clone()
+            });
+            self.loading_more = true;
+        }
+    }
+    /// whether a freshly synced mark belongs in the list on screen, search results wait for the end of the sync
+    fn fits_current_list(&self, mark: &MarkData) -> bool {
+        let query = match &self.current_query {
+            Some(query) => query.clone(),
+            None => self.search_query(),
+        };
+        if query.search.as_ref().map_or(false, |search| !search.trim().is_empty()) {
+            return false;
+        }
+        let in_category = match &query.category {
+            Some(cat) if query.include_subfolders => BookmarkApi::init().get_category_tree_ids(&cat.id).contains(&mark.category.id),
+            Some(cat) => cat.id == mark.category.id,
+            None => true,
+        };
+        let in_source = match &query.source_id {
+            Some(source_id) => mark.source.as_ref().map_or(false, |source| &source.id == source_id),
+            None => true,
+        };
+        let in_dates = match mark.date_added {
+            Some(added) => query.added_from.map_or(true, |from| added >= from) && query.added_to.map_or(true, |to| added <= to),
+            None => query.added_from.is_none() && query.added_to.is_none(),
+        };
+        in_category && in_source && in_dates
+    }
+    /// adds the card as soon as the mark is scraped, the whole list is loaded again in order once the sync is done
+    fn show_synced(&mut self, mark: &MarkData, new: bool) {
+        let id = match &mark.id {
+            Some(id) => id,
+            None => return,
+        };
+        if !self.fits_current_list(mark) {
+            return;
+        }
+        // a bookmark that is scraped again replaces its card
+        if let Some(item) = self.items.iter_mut().find(|item| item.data.id.as_ref() == Some(id)) {
+            *item = MarkComponent::new(mark.clone());
+            return;
+        }
+        if new {
+            self.total += 1;
+        }
+        // past the loaded pages the mark comes with load_more, adding it here would show it twice
+        let sort = self.current_query.as_ref().map_or(self.sort, |query| query.sort);
+        let on_loaded_pages = self.next_page.as_ref().map_or(true, |next| !next.precedes(sort, id, mark));
+        if on_loaded_pages {
+            self.items.push(MarkComponent::new(mark.clone()));
+        }
+    }
+    /// runs the current query again once a sync is done, with as many marks as are shown now
+    /// so the list stays where it was scrolled to
+    pub fn reload_after_sync(&mut self, state: &mut State) {
+        let query = match &self.current_query {
+            Some(query) => query.clone(),
+            None => {
+                let mut state = State::LoadItems(0, self.search_query());
+                self.apply_view_options(&mut state);
+                match state {
+                    State::LoadItems(_, query) => query,
+                    State::None => return,
+                }
+            }
+        };
+        let resync_len = self.items.len().max(self.page_size);
+        self.resync_len = Some(resync_len);
+        self.loading_more = false;
+        *state = State::LoadItems(0, MarksQuery {
+            after: None,
+            page_size: Some(resync_len),
+            ..query
+        });
+    }
+    /// reloads the current list from the first page, used when the sort or the date range change
+    fn reload(&mut self, state: &mut State) {
+        if self.date_range().is_err() {
+            return;
+        }
+        self.last_index = 0;
+        self.items = Vec::new();
+        self.resync_len = None;
+        let query = match &self.current_query {
+            Some(query) => MarksQuery { after: None, ..query.clone() },
+            None => self.search_query(),
+        };
+        *state = State::LoadItems(0, query);
+    }
+    /// parses the "added from" / "added to" inputs, the "to" day is included
+    fn date_range(&mut self) -> Result<(Option<i64>, Option<i64>), ()> {
+        let parse = |val: &String, end_of_day: bool| -> Result<Option<i64>, ()> {
+            if val.trim().is_empty() {
+                return Ok(None);
+            }
+            utils::parse_date(val, end_of_day).map(Some).ok_or(())
+        };
+        let range = parse(&self.added_from_val, false).and_then(|from| {
+            parse(&self.added_to_val, true).map(|to| (from, to))
+        });
+        self.date_error = match range {
+            Ok(_) => None,
+            Err(_) => Some(String::from("dates must look like YYYY-MM-DD")),
+        };
+        range
+    }
+    /// every list load goes through the sort and date range picked above the marks,
+    /// whichever component asked for it
+    pub fn apply_view_options(&mut self, state: &mut State) {
+        let sort = self.sort;
+        let page_size = self.page_size;
+        let (added_from, added_to) = self.date_range().unwrap_or((None, None));
+        if let State::LoadItems(_, query) = state {
+            query.sort = sort;
+            query.page_size = Some(page_size);
+            query.added_from = added_from;
+            query.added_to = added_to;
+        }
+    }
+    /// the query for the search box and source filter, an empty one loads every mark
+    fn search_query(&self) -> MarksQuery {
+        MarksQuery {
+            search: if self.search_input_val.is_empty() {
+                None
+            } else {
+                Some(self.search_input_val.to_string())
+            },
+            regex: self.regex_mode,
+            source_id: self.source_filter.as_ref().map(|source| source.id.clone()),
+            ..MarksQuery::default()
+        }
+    }
     fn render_search_input<'a>(state: &'a mut text_input::State, search_input_val: &'a String) -> TextInput<'a, MCMessage> {
-        TextInput::new(state, "search in bookmarks", search_input_val, MCMessage::SearchInputChanged)
+        TextInput::new(state, "search in bookmarks, e.g. rust site:github.com -tag:done", search_input_val, MCMessage::SearchInputChanged)
         .padding(15).width(Length::Fill).size(20)
         .style(style::StyledTextInput::new())
         .on_submit(MCMessage::Search)
     }
+    fn render_date_input<'a>(state: &'a mut text_input::State, placeholder: &str, val: &'a String, on_change: fn(String) -> MCMessage) -> TextInput<'a, MCMessage> {
+        TextInput::new(state, placeholder, val, on_change)
+        .padding(10).width(Length::FillPortion(1)).size(16)
+        .style(style::StyledTextInput::new())
+        .on_submit(MCMessage::ApplyDateFilter)
+    }
     fn render_content(&mut self) -> Element<MCMessage> {
         let items_length = &self.items.len().clone();
         let mut all_content = Column::new().push(Space::new(Length::Fill, Length::Units(20)));
         all_content = all_content.push(
-            Self::render_search_input(&mut self.search_input_state, &self.search_input_val)
-        )
-        .push(Space::new(Length::Fill, Length::Units(20)))
-        ;
+            Row::new()
+            .spacing(15)
+            .align_items(Align::Center)
+            .push(Self::render_search_input(&mut self.search_input_state, &self.search_input_val))
+            .push(Checkbox::new(self.regex_mode, "regex", MCMessage::RegexToggled).text_size(16))
+        );
+        if let Some(search_error) = &self.search_error {
+            all_content = all_content.push(Text::new(search_error).size(16).color(style::PINK));
+        }
+        all_content = all_content
+        .push(Space::new(Length::Fill, Length::Units(10)))
+        .push(
+            Row::new()
+            .spacing(10)
+            .align_items(Align::Center)
+            .push(
+                PickList::new(
+                    &mut self.sort_state,
+                    &SortOrder::ALL[..],
+                    Some(self.sort),
+                    MCMessage::SortSelected,
+                )
+                .width(Length::FillPortion(1))
+                .padding(10)
+            )
+            .push(
+                PickList::new(
+                    &mut self.page_size_state,
+                    config::page_sizes(),
+                    Some(self.page_size),
+                    MCMessage::PageSizeSelected,
+                )
+                .padding(10)
+            )
+            .push(Self::render_date_input(&mut self.added_from_state, "added from (YYYY-MM-DD)", &self.added_from_val, MCMessage::AddedFromChanged))
+            .push(Self::render_date_input(&mut self.added_to_state, "added to (YYYY-MM-DD)", &self.added_to_val, MCMessage::AddedToChanged))
+        );
+        if let Some(date_error) = &self.date_error {
+            all_content = all_content.push(Text::new(date_error).size(16).color(style::PINK));
+        }
+        all_content = all_content.push(Space::new(Length::Fill, Length::Units(20)));
+        if items_length > &0 {
+            all_content = all_content.push(
+                Text::new(format!("showing {} of {}", items_length, self.total))
+                .size(16)
+                .color(style::text_color())
+            );
+        }
         if items_length == &0 && self.current_cat_id.is_none() {
             all_content = all_content.push(
                 Text::new("no data to show").width(Length::Fill).size(20)
-                .color(style::TEXT_COLOR)
+                .color(style::text_color())
                 .horizontal_alignment(HorizontalAlignment::Center)
             )
             .push(Rule::horizontal(10))
@@ -239,7 +620,7 @@ impl MarkComponents {
         let mut splicer = 0;
         let mut count = 0;
         let marks_count = self.items.len();
-        let columns_count: u16 = 2;
+        let columns_count = user_config::get().columns;
         for item in self.items.iter_mut() {
             count += 1;
             row = row.push(item.view());
@@ -263,7 +644,16 @@ impl MarkComponents {
                 splicer += 1;
             }
         }
-        if items_length > &0 && self.has_more {
+        if self.loading_more {
+            all_content = all_content.push(
+                Column::new()
+                    .push(Text::new("loading more bookmarks...").size(18).color(style::text_color()))
+                    .push(Space::new(Length::Fill, Length::Units(30)))
+                    .width(Length::Fill)
+                    .align_items(Align::Center)
+            );
+        } else if items_length > &0 && self.has_more {
+            // scrolling down loads the next page, the button (or End) is there when it doesn't
             all_content = all_content.push(
                 Column::new()
                     .push(
@@ -275,6 +665,14 @@ impl MarkComponents {
                     .push(Space::new(Length::Fill, Length::Units(30)))
                     .align_items(Align::Center)
                 );
+        } else if items_length > &0 {
+            all_content = all_content.push(
+                Column::new()
+                    .push(Text::new(format!("that's all {} bookmarks", self.total)).size(16).color(style::secondary_color()))
+                    .push(Space::new(Length::Fill, Length::Units(30)))
+                    .width(Length::Fill)
+                    .align_items(Align::Center)
+            );
         }
         Container::new(all_content)
             .align_x(Align::Center)
@@ -282,7 +680,70 @@ impl MarkComponents {
             .padding(30)
             .into()
     }
+    /// the saved article of a mark, readable without the page being online
+    fn render_reader<'a>(
+        mark: &'a MarkData,
+        back_btn_state: &'a mut button::State,
+        goto_btn_state: &'a mut button::State,
+    ) -> Element<'a, MCMessage> {
+        let byline: Vec<String> = vec![
+            mark.meta.site_name.clone().unwrap_or_else(|| utils::domain_of(&mark.link)),
+            mark.meta.author.clone().unwrap_or_default(),
+            mark.meta.published.map(utils::format_date).unwrap_or_default(),
+        ]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect();
+        let mut article = Column::new()
+            .spacing(15)
+            .push(
+                Row::new()
+                .spacing(10)
+                .push(
+                    Button::new(back_btn_state, Text::new("back to bookmarks"))
+                    .padding(10)
+                    .style(style::StyledButton::primary())
+                    .on_press(MCMessage::CloseReader)
+                )
+                .push(
+                    Button::new(goto_btn_state, Text::new("open original"))
+                    .padding(10)
+                    .style(style::StyledButton::accent())
+                    .on_press(MCMessage::GotoClicked(mark.clone()))
+                )
+            )
+            .push(Text::new(&mark.title).size(32).color(style::text_color()))
+            .push(Text::new(byline.join(" · ")).size(16).color(style::secondary_color()))
+            .push(Rule::horizontal(10));
+        if mark.content.trim().is_empty() {
+            article = article.push(
+                Text::new("nothing was saved from this page, it was offline or empty when it was synced")
+                .size(18)
+                .color(style::text_color())
+            );
+        }
+        for block in mark.content.split("\n\n").filter(|block| !block.trim().is_empty()) {
+            let heading_level = block.chars().take_while(|c| *c == '#').count();
+            let text = if heading_level > 0 && block[heading_level..].starts_with(' ') {
+                Text::new(block[heading_level..].trim()).size(if heading_level <= 2 { 26 } else { 22 }).color(style::text_color())
+            } else if let Some(quote) = block.strip_prefix("> ") {
+                Text::new(quote).size(18).color(style::secondary_color())
+            } else if let Some(item) = block.strip_prefix("- ") {
+                Text::new(format!("• {}", item)).size(18).color(style::text_color())
+            } else {
+                Text::new(block.trim_start_matches("```\n").trim_end_matches("\n```")).size(18).color(style::text_color())
+            };
+            article = article.push(text);
+        }
+        Container::new(article)
+            .width(Length::Fill)
+            .padding(30)
+            .into()
+    }
     pub fn view(&mut self) -> Element<MCMessage> {
-        self.render_content()
+        match &self.reading {
+            Some(mark) => Self::render_reader(mark, &mut self.reader_back_btn_state, &mut self.reader_goto_btn_state),
+            None => self.render_content(),
+        }
     }
 }
@@ -3,3 +3,8 @@ pub mod mark_component;
 pub mod side_bar;
 pub mod header;
 pub mod state;
+pub mod sources;
+pub mod health;
+pub mod onboarding;
+pub mod diagnostics;
+pub mod scroll_watch;
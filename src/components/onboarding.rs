@@ -0,0 +1,138 @@
+use iced::{button, Align, Button, Checkbox, Clipboard, Column, Command, Container, Element, Length, Row, Rule, Text};
+
+use crate::services::bookmark_api::BookmarkApi;
+use crate::services::source::{SourceError, SourceInfo};
+use crate::style;
+
+use super::state::{Message, OnboardingMessage};
+
+/// the first-run screen, picks the profiles to read before the first sync
+#[derive(Debug, Clone)]
+pub struct OnboardingComponent {
+    sources: Vec<(SourceInfo, bool, Option<SourceError>)>,
+    pick_error: Option<String>,
+    pick_btn_state: button::State,
+    rescan_btn_state: button::State,
+    continue_btn_state: button::State,
+}
+
+impl OnboardingComponent {
+    pub fn new() -> Self {
+        OnboardingComponent {
+            sources: BookmarkApi::init().detect_sources(),
+            pick_error: None,
+            pick_btn_state: button::State::new(),
+            rescan_btn_state: button::State::new(),
+            continue_btn_state: button::State::new(),
+        }
+    }
+    /// at least one enabled source can be read
+    fn can_continue(&self) -> bool {
+        self.sources.iter().any(|(_, enabled, error)| *enabled && error.is_none())
+    }
+    /// the main view closes the screen and starts the sync on Continue
+    pub fn update(&mut self, message: OnboardingMessage, _clipboard: &mut Clipboard) -> Command<Message> {
+        match message {
+            OnboardingMessage::SourceToggled(info, enabled) => {
+                let mut bookmark_api = BookmarkApi::init();
+                bookmark_api.set_source_enabled(&info.id, enabled);
+                if let Some(source) = self.sources.iter_mut().find(|(source_info, _, _)| *source_info == info) {
+                    source.1 = enabled;
+                }
+                Command::none()
+            },
+            OnboardingMessage::PickFile => {
+                self.pick_error = None;
+                Command::perform(BookmarkApi::pick_bookmarks_file(), OnboardingMessage::FilePicked)
+                    .map(Message::OnboardingMessage)
+            },
+            OnboardingMessage::FilePicked(result) => {
+                if result.is_err() {
+                    self.pick_error = Some(String::from("the picked file couldn't be added"));
+                }
+                self.sources = BookmarkApi::init().detect_sources();
+                Command::none()
+            },
+            OnboardingMessage::Rescan => {
+                self.sources = BookmarkApi::init().detect_sources();
+                Command::none()
+            },
+            OnboardingMessage::Continue => {
+                BookmarkApi::init().finish_onboarding();
+                Command::none()
+            },
+        }
+    }
+    pub fn view(&mut self) -> Element<OnboardingMessage> {
+        let mut content = Column::new()
+            .spacing(15)
+            .push(Text::new("welcome to Roboza Press").size(32).color(style::text_color()))
+            .push(
+                Text::new("pick the browser profiles whose bookmarks you want to read, they can be changed later in the sidebar")
+                .size(16)
+                .color(style::secondary_color())
+            )
+            .push(Rule::horizontal(10));
+        if self.sources.is_empty() {
+            content = content.push(
+                Text::new("no browser bookmarks were found on this machine, pick a bookmarks file to start")
+                .size(18)
+                .color(style::text_color())
+            );
+        }
+        for (info, enabled, error) in self.sources.iter() {
+            let toggled_info = info.clone();
+            let mut source = Column::new()
+                .spacing(4)
+                .push(
+                    Checkbox::new(*enabled, info.to_string(), move |checked| {
+                        OnboardingMessage::SourceToggled(toggled_info.clone(), checked)
+                    })
+                    .text_size(18)
+                );
+            if let Some(error) = error {
+                source = source.push(Text::new(error.to_string()).size(14).color(style::PINK));
+            }
+            content = content.push(source);
+        }
+        if let Some(pick_error) = &self.pick_error {
+            content = content.push(Text::new(pick_error).size(14).color(style::PINK));
+        }
+        let can_continue = self.can_continue();
+        let mut continue_btn = Button::new(&mut self.continue_btn_state, Text::new("continue"))
+            .padding(10)
+            .style(style::StyledButton::accent());
+        if can_continue {
+            continue_btn = continue_btn.on_press(OnboardingMessage::Continue);
+        }
+        content = content
+            .push(Rule::horizontal(10))
+            .push(
+                Row::new()
+                .spacing(10)
+                .align_items(Align::Center)
+                .push(
+                    Button::new(&mut self.pick_btn_state, Text::new("pick a bookmarks file"))
+                    .padding(10)
+                    .style(style::StyledButton::primary())
+                    .on_press(OnboardingMessage::PickFile)
+                )
+                .push(
+                    Button::new(&mut self.rescan_btn_state, Text::new("look again"))
+                    .padding(10)
+                    .style(style::StyledButton::primary())
+                    .on_press(OnboardingMessage::Rescan)
+                )
+                .push(continue_btn)
+            )
+            .push(
+                Text::new("a Chrome, Edge, Brave or Vivaldi \"Bookmarks\" file or an html bookmarks export can be picked")
+                .size(14)
+                .color(style::secondary_color())
+            );
+        Container::new(content)
+            .width(Length::Fill)
+            .padding(30)
+            .into()
+    }
+}
@@ -0,0 +1,88 @@
+use iced_native::widget::{column, scrollable, Scrollable};
+use iced_native::{event, layout, mouse, overlay, Clipboard, Element, Event, Hasher, Layout, Length, Point, Rectangle, Size, Widget};
+
+/// a scrollable that tells how tall it and its content are laid out every time it scrolls,
+/// iced 0.3's own one has no scroll message
+pub struct ScrollWatch<'a, Message, Renderer: scrollable::Renderer> {
+    scrollable: Scrollable<'a, Message, Renderer>,
+    on_scroll: Box<dyn Fn(f32, f32) -> Message + 'a>,
+}
+
+impl<'a, Message, Renderer: scrollable::Renderer> ScrollWatch<'a, Message, Renderer> {
+    /// on_scroll gets the height of the viewport and the height of the content
+    pub fn new(scrollable: Scrollable<'a, Message, Renderer>, on_scroll: impl Fn(f32, f32) -> Message + 'a) -> Self {
+        ScrollWatch {
+            scrollable,
+            on_scroll: Box::new(on_scroll),
+        }
+    }
+}
+
+/// how far the scrollable is scrolled. the message of on_scroll is handled after the widgets saw
+/// the event, so the state has already moved by then. it clamps the offset to the content every
+/// time it scrolls, the bounds given here don't clamp it any further
+pub fn offset(state: &scrollable::State) -> f32 {
+    state.offset(Rectangle::with_size(Size::ZERO), Rectangle::with_size(Size::INFINITY)) as f32
+}
+
+impl<'a, Message, Renderer> Widget<Message, Renderer> for ScrollWatch<'a, Message, Renderer>
+where
+    Renderer: 'a + scrollable::Renderer + column::Renderer,
+{
+    fn width(&self) -> Length {
+        Widget::<Message, Renderer>::width(&self.scrollable)
+    }
+    fn height(&self) -> Length {
+        Widget::<Message, Renderer>::height(&self.scrollable)
+    }
+    fn layout(&self, renderer: &Renderer, limits: &layout::Limits) -> layout::Node {
+        Widget::<Message, Renderer>::layout(&self.scrollable, renderer, limits)
+    }
+    fn draw(
+        &self,
+        renderer: &mut Renderer,
+        defaults: &Renderer::Defaults,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+    ) -> Renderer::Output {
+        Widget::<Message, Renderer>::draw(&self.scrollable, renderer, defaults, layout, cursor_position, viewport)
+    }
+    fn hash_layout(&self, state: &mut Hasher) {
+        Widget::<Message, Renderer>::hash_layout(&self.scrollable, state)
+    }
+    fn on_event(
+        &mut self,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        messages: &mut Vec<Message>,
+    ) -> event::Status {
+        // the wheel, or the scrollbar being dragged
+        let scrolls = matches!(
+            event,
+            Event::Mouse(mouse::Event::WheelScrolled { .. }) | Event::Mouse(mouse::Event::CursorMoved { .. })
+        );
+        let status = self.scrollable.on_event(event, layout, cursor_position, renderer, clipboard, messages);
+        if scrolls && status == event::Status::Captured {
+            let content_height = layout.children().next().map_or(0.0, |content| content.bounds().height);
+            messages.push((self.on_scroll)(layout.bounds().height, content_height));
+        }
+        status
+    }
+    fn overlay(&mut self, layout: Layout<'_>) -> Option<overlay::Element<'_, Message, Renderer>> {
+        Widget::<Message, Renderer>::overlay(&mut self.scrollable, layout)
+    }
+}
+
+impl<'a, Message, Renderer> From<ScrollWatch<'a, Message, Renderer>> for Element<'a, Message, Renderer>
+where
+    Renderer: 'a + scrollable::Renderer + column::Renderer,
+    Message: 'a,
+{
+    fn from(watch: ScrollWatch<'a, Message, Renderer>) -> Element<'a, Message, Renderer> {
+        Element::new(watch)
+    }
+}
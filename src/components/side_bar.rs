@@ -1,6 +1,7 @@
 use super::category::CategoriesComponent;
-use super::state::{SideBarMessage, Events, State};
-use crate::services::bookmark_api::BookmarkApi;
+use super::sources::SourcesComponent;
+use super::state::{SideBarMessage, CategoryMessage, Events, Message, State};
+use crate::services::{archive, bookmark_api::BookmarkApi};
 use iced::{
     Column, Element, Clipboard, Command
 };
@@ -8,15 +9,18 @@ use iced::{
 #[derive(Debug, Clone)]
 pub struct SideBar {
     categories_component: CategoriesComponent,
+    sources_component: SourcesComponent,
 }
 
 impl SideBar {
     pub fn new() -> Self {
-        let mut bookmarks_api = BookmarkApi::init();
+        let mut categories = {
+            let mut bookmarks_api = BookmarkApi::init();
+            bookmarks_api.get_categories().to_vec()
+        };
         SideBar {
-            categories_component: CategoriesComponent::new(
-                &mut bookmarks_api.get_categories().to_vec(),
-            ),
+            categories_component: CategoriesComponent::new(&mut categories),
+            sources_component: SourcesComponent::new(),
         }
     }
     pub fn update(
@@ -26,11 +30,27 @@ impl SideBar {
         state: &mut State
     ) -> Command<Events> {
             match message {
-                SideBarMessage::CategoryMessage(category_message) => self.categories_component.update(category_message, clipboard, state)
+                SideBarMessage::CategoryMessage(category_message) => {
+                    if let CategoryMessage::CategoryClicked(_) = category_message {
+                        self.sources_component.reset_filter();
+                    }
+                    self.categories_component.update(category_message, clipboard, state)
+                },
+                SideBarMessage::SourceMessage(source_message) => self.sources_component.update(source_message, clipboard, state)
             }
     }
+    /// measures the disk space of the archived pages, the archives dir can be big
+    pub fn measure_archives() -> Command<Message> {
+        Command::perform(archive::storage_used(), CategoryMessage::StorageMeasured)
+            .map(|m| Message::SideBarMessage(SideBarMessage::CategoryMessage(m)))
+    }
     pub fn view(&mut self) -> Element<SideBarMessage> {
         Column::new()
+            .push(
+                self.sources_component
+                    .view()
+                    .map(|ms| SideBarMessage::SourceMessage(ms)),
+            )
             .push(
                 self.categories_component
                     .view()
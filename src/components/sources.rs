@@ -0,0 +1,135 @@
+use iced::{pick_list, Align, Checkbox, Clipboard, Column, Command, Element, Length, PickList, Rule, Text};
+use std::collections::HashMap;
+use std::fmt;
+
+use super::state::{Events, SourceMessage, State};
+use crate::services::{bookmark_api::BookmarkApi, source::SourceInfo};
+use crate::style;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceOption {
+    All,
+    Source(SourceInfo),
+}
+
+impl fmt::Display for SourceOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceOption::All => write!(f, "all sources"),
+            SourceOption::Source(info) => write!(f, "{}", info),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SourcesComponent {
+    sources: Vec<(SourceInfo, bool)>,
+    visible_sources: Vec<(SourceInfo, bool)>,
+    // source id to why its bookmarks couldn't be read
+    read_errors: HashMap<String, String>,
+    filter_state: pick_list::State<SourceOption>,
+    selected: SourceOption,
+}
+
+impl SourcesComponent {
+    pub fn new() -> Self {
+        let (sources, visible_sources, read_errors) = {
+            let bookmark_api = BookmarkApi::init();
+            (bookmark_api.get_sources(), bookmark_api.get_visible_sources(), Self::read_errors(&bookmark_api))
+        };
+        SourcesComponent {
+            sources,
+            visible_sources,
+            read_errors,
+            filter_state: pick_list::State::default(),
+            selected: SourceOption::All,
+        }
+    }
+    fn read_errors(bookmark_api: &BookmarkApi) -> HashMap<String, String> {
+        bookmark_api
+            .get_read_errors()
+            .into_iter()
+            .map(|(info, error)| (info.id, error.to_string()))
+            .collect()
+    }
+    pub fn reset_filter(&mut self) {
+        self.selected = SourceOption::All;
+    }
+    fn filter_options(&self) -> Vec<SourceOption> {
+        let mut options = vec![SourceOption::All];
+        options.extend(
+            self.visible_sources
+                .iter()
+                .map(|(info, _)| SourceOption::Source(info.clone())),
+        );
+        options
+    }
+    pub fn update(
+        &mut self,
+        message: SourceMessage,
+        _clipboard: &mut Clipboard,
+        _state: &mut State
+    ) -> Command<Events> {
+        match message {
+            SourceMessage::SourceToggled(info, enabled) => {
+                let mut bookmark_api = BookmarkApi::init();
+                bookmark_api.set_source_enabled(&info.id, enabled);
+                self.sources = bookmark_api.get_sources();
+                self.visible_sources = bookmark_api.get_visible_sources();
+                if !enabled && self.selected == SourceOption::Source(info) {
+                    self.selected = SourceOption::All;
+                }
+            },
+            SourceMessage::FilterSelected(option) => {
+                self.selected = option;
+            },
+            SourceMessage::Reload(_) => {
+                let bookmark_api = BookmarkApi::init();
+                self.sources = bookmark_api.get_sources();
+                self.visible_sources = bookmark_api.get_visible_sources();
+                self.read_errors = Self::read_errors(&bookmark_api);
+                if !self.filter_options().contains(&self.selected) {
+                    self.selected = SourceOption::All;
+                }
+            }
+        };
+        Command::none()
+    }
+    pub fn view(&mut self) -> Element<SourceMessage> {
+        let options = self.filter_options();
+        let mut content = Column::new()
+            .width(Length::Fill)
+            .spacing(10)
+            .padding(15)
+            .align_items(Align::Start)
+            .push(Text::new("Bookmark Sources").size(18).color(style::text_color()))
+            .push(Rule::horizontal(10));
+        if self.sources.is_empty() {
+            content = content.push(Text::new("no browser bookmarks found").color(style::text_color()));
+        }
+        for (info, enabled) in self.sources.iter() {
+            let toggled_info = info.clone();
+            content = content.push(
+                Checkbox::new(*enabled, info.to_string(), move |checked| {
+                    SourceMessage::SourceToggled(toggled_info.clone(), checked)
+                })
+                .text_size(16)
+            );
+            if let Some(error) = self.read_errors.get(&info.id) {
+                content = content.push(Text::new(error).size(14).color(style::PINK));
+            }
+        }
+        content
+            .push(
+                PickList::new(
+                    &mut self.filter_state,
+                    options,
+                    Some(self.selected.clone()),
+                    SourceMessage::FilterSelected,
+                )
+                .width(Length::Fill)
+                .padding(10)
+            )
+            .into()
+    }
+}
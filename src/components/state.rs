@@ -1,4 +1,7 @@
-use crate::{services::bookmark_api::{MarkData, BookmarkCategory, MarkMeta}, utils::Error};
+use crate::{services::{bookmark_api::{MarkData, BookmarkCategory, MarkMeta, MarksQuery, SortOrder}, health::HealthReport, source::SourceInfo, sync::SyncProgress}, utils::Error};
+use crate::subscriptions::scroll::ScrollEvent;
+use std::time::Instant;
+use super::sources::SourceOption;
 
 #[derive(Debug, Clone)]
 pub enum Events{
@@ -9,29 +12,93 @@ pub enum Events{
 pub enum MCMessage {
     LoadMark(MarkMeta),
     GotoClicked(MarkData),
+    ReadClicked(MarkData),
+    OpenArchiveClicked(MarkData),
+    CloseReader,
     LoadMore(usize),
+    /// the marks were scrolled: the offset, the height they are shown in and their own height
+    Scrolled(f32, f32, f32),
     Refresh(Result<(), Error>),
     SearchInputChanged(String),
+    RegexToggled(bool),
     Search,
-    CategoryClicked
+    CategoryClicked,
+    SourceSelected(Option<SourceInfo>),
+    SortSelected(SortOrder),
+    PageSizeSelected(usize),
+    AddedFromChanged(String),
+    AddedToChanged(String),
+    ApplyDateFilter,
+    /// a mark the running sync just saved, shown before the sync is done, and whether it's a new one
+    MarkSynced(MarkData, bool)
 }
 
 #[derive(Clone, Debug)]
 pub enum CategoryMessage {
     CategoryClicked(BookmarkCategory),
+    ToggleExpanded(String),
+    IncludeSubfoldersToggled(bool),
+    ArchiveToggled(String, bool),
+    /// the disk space of the archived pages, measured off the ui thread
+    StorageMeasured(u64),
+    Reload(Result<(), Error>)
+}
+
+#[derive(Clone, Debug)]
+pub enum SourceMessage {
+    SourceToggled(SourceInfo, bool),
+    FilterSelected(SourceOption),
     Reload(Result<(), Error>)
 }
 
 #[derive(Clone, Debug)]
 pub enum SideBarMessage {
     CategoryMessage(CategoryMessage),
+    SourceMessage(SourceMessage),
 }
 #[derive(Clone, Debug)]
 pub enum HeaderMessage{
     Loading,
     Loaded,
     ClearCache,
-    Resync
+    Resync,
+    Import,
+    Export,
+    Exported(Result<(), Error>),
+    ProfileSelected(SourceOption),
+    BookmarksChanged,
+    ShowHealth,
+    ShowDiagnostics,
+    SyncProgress(SyncProgress),
+    Tick(Instant),
+    DismissToast,
+    ToggleErrorLog,
+    ClearErrors
+}
+
+#[derive(Clone, Debug)]
+pub enum DiagnosticsMessage {
+    FilterChanged(String),
+    Refresh,
+    Close
+}
+
+#[derive(Clone, Debug)]
+pub enum OnboardingMessage {
+    SourceToggled(SourceInfo, bool),
+    PickFile,
+    FilePicked(Result<(), Error>),
+    Rescan,
+    Continue
+}
+
+#[derive(Clone, Debug)]
+pub enum HealthMessage {
+    Loaded(Result<HealthReport, Error>),
+    UpdateRedirects,
+    RemoveDead,
+    FixesSaved(Result<(), Error>),
+    Close
 }
 
 #[derive(Clone, Debug)]
@@ -39,13 +106,20 @@ pub enum Message {
     MCEvent(MCMessage),
     SideBarMessage(SideBarMessage),
     HeaderMessage(HeaderMessage),
+    HealthMessage(HealthMessage),
+    DiagnosticsMessage(DiagnosticsMessage),
+    OnboardingMessage(OnboardingMessage),
     Events(Events),
     Syncing(Result<(), Error>),
-    Synced(())
+    Synced(Vec<Error>),
+    BodyScroll(ScrollEvent),
+    /// the body scrollable moved, the height of its viewport and of its content
+    BodyScrolled(f32, f32),
+    DismissConfigProblems
 }
 
 #[derive(Clone, Debug)]
 pub enum State{
-    LoadItems(usize, Option<BookmarkCategory>, Option<String>),
+    LoadItems(usize, MarksQuery),
     None
 }
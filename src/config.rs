@@ -1,15 +1,58 @@
-use std::{path::{PathBuf, Path}, env, str::FromStr};
+use std::{path::{PathBuf, Path}, env, str::FromStr, fs};
+use crate::user_config;
 
-pub const L_INUX_UBUNTU_BOOKMARKS_PATH: &str = "google-chrome/Default/Bookmarks";
-pub const WINDOWS_BOOKMARKS_PATH: &str = "Google/Chrome/User Data/Default/Bookmarks";
-pub const CACHE_MAIN_DIR: &str = "RobozaPress";
-pub const CACHE_FILE_PATH: &str = "robozaPress/robozaPress.json";
-pub const CACHE_IMG_PATH: &str = "robozaPress/images";
+pub const L_INUX_FIREFOX_PROFILES_PATH: &str = ".mozilla/firefox";
+pub const WINDOWS_FIREFOX_PROFILES_PATH: &str = "Mozilla/Firefox";
+pub const CHROMIUM_DEFAULT_PROFILE: &str = "Default";
+pub const CHROMIUM_BOOKMARKS_FILE: &str = "Bookmarks";
+pub const CHROMIUM_LOCAL_STATE_FILE: &str = "Local State";
+pub const FIREFOX_PROFILES_INI: &str = "profiles.ini";
+pub const FIREFOX_PLACES_FILE: &str = "places.sqlite";
+/// every cache path is inside this dir of the cache dir
+pub const CACHE_MAIN_DIR: &str = "robozaPress";
+pub const CACHE_FILE_PATH: &str = "robozaPress.json";
+pub const CACHE_LIBRARIES_DIR: &str = "libraries";
+pub const DB_FILE_PATH: &str = "robozaPress.db";
+pub const CACHE_IMG_PATH: &str = "images";
+pub const CACHE_PLACES_COPY_DIR: &str = "places";
+pub const SOURCES_FILE_PATH: &str = "sources.json";
+pub const CACHE_IMPORTS_DIR: &str = "imports";
+pub const SYNC_STATE_FILE_PATH: &str = "sync_state.json";
+pub const CACHE_ARCHIVES_PATH: &str = "archives";
+pub const ARCHIVE_SETTINGS_FILE_PATH: &str = "archive.json";
+pub const LINK_FIXES_FILE_PATH: &str = "link_fixes.json";
+pub const LOG_FILE_PATH: &str = "logs/robozaPress.log";
 pub const DEFAULT_IMG_PATH: &str = "default.png";
 pub const ASSETS_DIR_PATH: &str = "assets";
 pub const LOADER_ICON_PATH: &str = "loader-icon.svg";
+pub const PAGE_SIZES: [usize; 4] = [12, 24, 48, 96];
+
+/// the page sizes offered in the picker, with the configured one among them
+pub fn page_sizes() -> Vec<usize> {
+    let mut sizes = PAGE_SIZES.to_vec();
+    let page_size = user_config::get().page_size;
+    if !sizes.contains(&page_size) {
+        sizes.push(page_size);
+        sizes.sort_unstable();
+    }
+    sizes
+}
+
+/// the cache_dir of the config, or the platform cache dir
+pub fn get_cache_dir() -> PathBuf {
+    match &user_config::get().cache_dir {
+        Some(dir) => dir.clone(),
+        None => dirs::cache_dir().unwrap_or_else(env::temp_dir),
+    }
+}
+
+/// the cache paths below are all inside it, a flush removes it as a whole
+pub fn get_cache_main_dir() -> PathBuf {
+    get_cache_dir().join(CACHE_MAIN_DIR)
+}
+
 pub fn get_full_img_cache_path() -> PathBuf {
-    Path::new(dirs::cache_dir().unwrap().as_path()).join(CACHE_IMG_PATH)
+    get_cache_main_dir().join(CACHE_IMG_PATH)
 }
 
 pub fn get_default_image_path() -> PathBuf {
@@ -19,22 +62,115 @@ pub fn get_default_image_path() -> PathBuf {
 pub fn get_loader_icon_path() -> PathBuf {
     Path::new(ASSETS_DIR_PATH).join(LOADER_ICON_PATH)
 }
-pub fn get_os_config_path() -> PathBuf {
+
+fn detect_os() -> String {
+    env::consts::OS.to_string()
+}
+
+/// None on the systems firefox isn't looked for on
+pub fn get_firefox_profiles_dir() -> Option<PathBuf> {
     let current_os: String = detect_os().to_lowercase();
     match current_os.as_str() {
-        "windows" => Path::new(dirs::cache_dir().unwrap().as_path()).join(WINDOWS_BOOKMARKS_PATH),
-        "linux" => Path::new(dirs::config_dir().unwrap().as_path()).join(L_INUX_UBUNTU_BOOKMARKS_PATH),
-        _ => panic!("not supported os"),
+        "windows" => dirs::config_dir().map(|dir| dir.join(WINDOWS_FIREFOX_PROFILES_PATH)),
+        "linux" => dirs::home_dir().map(|dir| dir.join(L_INUX_FIREFOX_PROFILES_PATH)),
+        _ => None,
     }
 }
 
-fn detect_os() -> String {
-    env::consts::OS.to_string()
+/// reads firefox profiles.ini and returns the places.sqlite path of the default profile
+pub fn get_firefox_places_path() -> Option<PathBuf> {
+    let profiles_dir = get_firefox_profiles_dir()?;
+    let ini = fs::read_to_string(profiles_dir.join(FIREFOX_PROFILES_INI)).ok()?;
+    let mut install_default: Option<String> = None;
+    let mut profile_default: Option<(String, bool)> = None;
+    let mut first_profile: Option<(String, bool)> = None;
+    let mut section = String::new();
+    let mut path: Option<String> = None;
+    let mut is_relative = true;
+    let mut is_default = false;
+    // the trailing empty section flushes the last profile
+    for line in ini.lines().map(|line| line.trim()).chain(std::iter::once("[]")) {
+        if line.starts_with('[') && line.ends_with(']') {
+            if section.starts_with("Profile") && path.is_some() {
+                let profile = (path.take().unwrap(), is_relative);
+                if is_default {
+                    profile_default = Some(profile.clone());
+                }
+                if first_profile.is_none() {
+                    first_profile = Some(profile);
+                }
+            }
+            section = line[1..line.len() - 1].to_string();
+            path = None;
+            is_relative = true;
+            is_default = false;
+            continue;
+        }
+        match line.split_once('=') {
+            Some(("Default", val)) if section.starts_with("Install") => install_default = Some(val.to_string()),
+            Some(("Default", val)) => is_default = val == "1",
+            Some(("Path", val)) => path = Some(val.to_string()),
+            Some(("IsRelative", val)) => is_relative = val == "1",
+            _ => (),
+        }
+    }
+    let (profile_path, relative) = match install_default {
+        Some(install_path) => (install_path, true),
+        None => profile_default.or(first_profile)?,
+    };
+    let places_path = if relative {
+        profiles_dir.join(profile_path).join(FIREFOX_PLACES_FILE)
+    } else {
+        PathBuf::from(profile_path).join(FIREFOX_PLACES_FILE)
+    };
+    if places_path.is_file() {
+        Some(places_path)
+    } else {
+        None
+    }
 }
-pub fn get_bookmarks_path() -> String {
-    get_os_config_path().display().to_string()
+
+pub fn get_places_copy_path(source_id: &str) -> PathBuf {
+    get_cache_main_dir().join(CACHE_PLACES_COPY_DIR).join(format!("{}.sqlite", source_id))
 }
 
 pub fn get_cache_file_path() -> PathBuf {
-    Path::new(dirs::cache_dir().unwrap().as_path()).join(Path::new(CACHE_FILE_PATH))
-}
\ No newline at end of file
+    get_cache_main_dir().join(CACHE_FILE_PATH)
+}
+
+/// the old jfs caches, one dir per browser profile, only read to migrate them
+pub fn get_libraries_dir() -> PathBuf {
+    get_cache_main_dir().join(CACHE_LIBRARIES_DIR)
+}
+
+pub fn get_db_file_path() -> PathBuf {
+    get_cache_main_dir().join(DB_FILE_PATH)
+}
+
+pub fn get_sources_file_path() -> PathBuf {
+    get_cache_main_dir().join(SOURCES_FILE_PATH)
+}
+
+pub fn get_imports_dir() -> PathBuf {
+    get_cache_main_dir().join(CACHE_IMPORTS_DIR)
+}
+
+pub fn get_sync_state_file_path() -> PathBuf {
+    get_cache_main_dir().join(SYNC_STATE_FILE_PATH)
+}
+
+pub fn get_archives_dir() -> PathBuf {
+    get_cache_main_dir().join(CACHE_ARCHIVES_PATH)
+}
+
+pub fn get_archive_settings_file_path() -> PathBuf {
+    get_cache_main_dir().join(ARCHIVE_SETTINGS_FILE_PATH)
+}
+
+pub fn get_link_fixes_file_path() -> PathBuf {
+    get_cache_main_dir().join(LINK_FIXES_FILE_PATH)
+}
+
+pub fn get_log_file_path() -> PathBuf {
+    get_cache_main_dir().join(LOG_FILE_PATH)
+}
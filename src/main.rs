@@ -4,44 +4,90 @@ pub mod utils;
 mod components;
 pub mod subscriptions;
 pub mod config;
+pub mod user_config;
 use iced::{
-    executor, scrollable, Application, Clipboard, Column, Command, Container, Element, Length, Row,
-    Scrollable, Settings, Space, Subscription
+    button, executor, scrollable, Application, Button, Clipboard, Column, Command, Container, Element, Length, Row,
+    Scrollable, Settings, Space, Subscription, Text
 };
-use components::{mark_component::MarkComponents, side_bar::SideBar, state::{Message, HeaderMessage, CategoryMessage, SideBarMessage}, state::State, state::MCMessage, header::Header};
-use services::bookmark_api::{BookmarkApi, BookmarkCategory};
+use components::{mark_component::MarkComponents, side_bar::SideBar, state::{Message, HeaderMessage, HealthMessage, DiagnosticsMessage, OnboardingMessage, CategoryMessage, SideBarMessage, SourceMessage}, state::State, state::MCMessage, header::Header, health::HealthComponent, diagnostics::DiagnosticsComponent, onboarding::OnboardingComponent, scroll_watch::{self, ScrollWatch}, sources::SourceOption};
+use services::bookmark_api::BookmarkApi;
+use services::sync::SyncEvent;
+use subscriptions::scroll::ScrollEvent;
+use std::time::Duration;
 
 #[tokio::main]
 pub async fn main() -> iced::Result {
-    let mut config = Settings::default();
-    config.window.max_size = Some((1200, 900));
-    config.window.min_size = Some((1200, 900));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let startup = user_config::load(&args);
+    if startup.show_help {
+        println!("{}", user_config::USAGE);
+        return Ok(());
+    }
+    let window_size = (startup.config.window_width, startup.config.window_height);
+    user_config::init(startup.config);
+    services::logging::init(user_config::get().verbose);
+    // the config problems are shown above the header instead of stopping the app
+    let mut config = Settings::with_flags(startup.problems);
+    config.window.size = window_size;
+    config.window.max_size = Some(window_size);
+    config.window.min_size = Some(window_size);
     Mark::run(config)
 }
 
+/// what the body shows next to the sidebar
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BodyView {
+    Marks,
+    Health,
+    Diagnostics
+}
+
 struct Mark {
     body_scroll: scrollable::State,
     sidebar_scroll: scrollable::State,
     header: Header,
     mark_components: MarkComponents,
     side_bar: SideBar,
-    state: State
+    health: HealthComponent,
+    diagnostics: DiagnosticsComponent,
+    body_view: BodyView,
+    // the first-run screen, shown instead of everything else until it's closed
+    onboarding: Option<OnboardingComponent>,
+    state: State,
+    config_problems: Vec<String>,
+    dismiss_problems_btn_state: button::State
 }
 
 impl Application for Mark {
     type Executor = executor::Default;
     type Message = Message;
-    type Flags = ();
-    fn new(_flags: ()) -> (Mark, Command<Message>) {
-        let app: Mark = Mark {
+    type Flags = Vec<String>;
+    fn new(config_problems: Vec<String>) -> (Mark, Command<Message>) {
+        let mut app: Mark = Mark {
             body_scroll: scrollable::State::new(),
             sidebar_scroll: scrollable::State::new(),
             mark_components: MarkComponents::new(),
             header: Header::new(),
             side_bar: SideBar::new(),
-            state: State::None
+            health: HealthComponent::new(),
+            diagnostics: DiagnosticsComponent::new(),
+            body_view: BodyView::Marks,
+            onboarding: None,
+            state: State::None,
+            config_problems,
+            dismiss_problems_btn_state: button::State::new()
         };
-        (app, Command::perform(BookmarkApi::perform_load(), Message::Syncing))
+        let needs_onboarding = BookmarkApi::init().needs_onboarding();
+        if needs_onboarding {
+            // nothing is synced before the profiles are picked
+            app.onboarding = Some(OnboardingComponent::new());
+            return (app, Command::none());
+        }
+        let command = Command::batch([
+            app.header.request_sync(),
+            SideBar::measure_archives()
+        ]);
+        (app, command)
     }
     fn title(&self) -> String {
         String::from("Roboza Press")
@@ -54,6 +100,11 @@ impl Application for Mark {
     ) -> Command<Self::Message> {
         match message {
             Message::MCEvent(mcmsg) => {
+                if let MCMessage::LoadMark(meta) = &mcmsg {
+                    if let Some(error) = &meta.error {
+                        self.header.report(vec![error.clone()]);
+                    }
+                }
                 self.mark_components.update(&mcmsg, clipboard, &mut self.state);
                 Command::none()
             }
@@ -63,24 +114,138 @@ impl Application for Mark {
                         CategoryMessage::CategoryClicked(_) => {
                             self.mark_components.update(&MCMessage::CategoryClicked, clipboard, &mut self.state);
                         },
+                        // the sync archives the pages of the newly picked folder
+                        CategoryMessage::ArchiveToggled(_, true) => {
+                            let command = self.side_bar.update(ms, clipboard, &mut self.state).map(|event| Message::Events(event));
+                            return Command::batch([
+                                command,
+                                self.header.request_sync()
+                            ]);
+                        },
+                        // the archives of the unticked folder are already gone
+                        CategoryMessage::ArchiveToggled(_, false) => {
+                            let command = self.side_bar.update(ms, clipboard, &mut self.state).map(|event| Message::Events(event));
+                            return Command::batch([
+                                command,
+                                SideBar::measure_archives()
+                            ]);
+                        },
+                        _ => ()
+                    },
+                    SideBarMessage::SourceMessage(ref source_ms) => match source_ms {
+                        SourceMessage::FilterSelected(option) => {
+                            let source = match option {
+                                SourceOption::All => None,
+                                SourceOption::Source(info) => Some(info.clone()),
+                            };
+                            self.mark_components.update(&MCMessage::SourceSelected(source), clipboard, &mut self.state);
+                        },
+                        SourceMessage::SourceToggled(_, _) => {
+                            let command = self.side_bar.update(ms, clipboard, &mut self.state).map(|event| Message::Events(event));
+                            return Command::batch([
+                                command,
+                                self.header.request_sync()
+                            ]);
+                        },
                         _ => ()
                     },
                 };
-                self.side_bar.update(ms, clipboard, &mut self.state).map(|event| Message::Events(event))
+                let command = self.side_bar.update(ms, clipboard, &mut self.state).map(|event| Message::Events(event));
+                self.mark_components.apply_view_options(&mut self.state);
+                command
             },
             Message::HeaderMessage(m) => {
+                if let HeaderMessage::ProfileSelected(_) = m {
+                    self.side_bar.update(SideBarMessage::SourceMessage(SourceMessage::FilterSelected(SourceOption::All)), clipboard, &mut self.state);
+                    self.mark_components.update(&MCMessage::SourceSelected(None), clipboard, &mut self.state);
+                }
+                if let HeaderMessage::ShowHealth = m {
+                    self.body_view = BodyView::Health;
+                    return self.health.open();
+                }
+                if let HeaderMessage::ShowDiagnostics = m {
+                    self.body_view = BodyView::Diagnostics;
+                    self.diagnostics.refresh();
+                    return Command::none();
+                }
                 self.header.update(m, clipboard, &mut self.state)
             }
+            Message::HealthMessage(m) => {
+                if let HealthMessage::Loaded(Err(error)) | HealthMessage::FixesSaved(Err(error)) = &m {
+                    self.header.report(vec![error.clone()]);
+                }
+                match m {
+                    HealthMessage::Close => {
+                        self.body_view = BodyView::Marks;
+                        Command::none()
+                    },
+                    // the fixes are laid over the bookmarks by the sync
+                    HealthMessage::FixesSaved(_) => Command::batch([
+                        self.health.update(m, clipboard),
+                        self.header.request_sync()
+                    ]),
+                    _ => self.health.update(m, clipboard)
+                }
+            }
+            Message::DiagnosticsMessage(m) => {
+                if let DiagnosticsMessage::Close = m {
+                    self.body_view = BodyView::Marks;
+                }
+                self.diagnostics.update(m, clipboard)
+            },
             Message::Events(_) => {
                 Command::none()
             },
-            Message::Syncing(_) => {
-                // println!()
+            Message::OnboardingMessage(m) => {
+                if let OnboardingMessage::FilePicked(Err(error)) = &m {
+                    self.header.report(vec![error.clone()]);
+                }
+                let closed = matches!(m, OnboardingMessage::Continue);
+                let command = match self.onboarding.as_mut() {
+                    Some(onboarding) => onboarding.update(m, clipboard),
+                    None => Command::none(),
+                };
+                if !closed {
+                    return command;
+                }
+                self.onboarding = None;
+                Command::batch([
+                    command,
+                    self.header.request_sync()
+                ])
+            },
+            Message::DismissConfigProblems => {
+                self.config_problems.clear();
+                Command::none()
+            },
+            Message::Syncing(result) => {
+                if let Err(error) = result {
+                    self.header.report(vec![error]);
+                }
                 self.header.update(HeaderMessage::Loading, clipboard, &mut self.state)
             },
-            Message::Synced(_) => {
+            Message::Synced(errors) => {
+                self.header.report(errors);
+                // the events of the sync that just finished
+                if self.body_view == BodyView::Diagnostics {
+                    self.diagnostics.refresh();
+                }
                 self.header.update(HeaderMessage::Loaded, clipboard, &mut self.state);
-                self.state = State::LoadItems(0, Some(BookmarkCategory::default()), None);
+                // the sources that couldn't be read are only known once the sync is done
+                self.side_bar.update(SideBarMessage::SourceMessage(SourceMessage::Reload(Ok(()))), clipboard, &mut self.state);
+                self.mark_components.reload_after_sync(&mut self.state);
+                // the sync archived the pages of the ticked folders
+                SideBar::measure_archives()
+            }
+            Message::BodyScrolled(viewport_height, content_height) => {
+                if self.body_view == BodyView::Marks {
+                    let offset = scroll_watch::offset(&self.body_scroll);
+                    self.mark_components.update(&MCMessage::Scrolled(offset, viewport_height, content_height), clipboard, &mut self.state);
+                }
+                Command::none()
+            }
+            Message::BodyScroll(ScrollEvent::LoadMoreKey) => {
+                self.mark_components.update(&MCMessage::LoadMore(self.mark_components.last_index()), clipboard, &mut self.state);
                 Command::none()
             }
         }
@@ -89,6 +254,30 @@ impl Application for Mark {
         let mut content = Column::new()
             .width(Length::Fill)
             .spacing(10);
+        if !self.config_problems.is_empty() {
+            let mut problems = Column::new()
+                .spacing(5)
+                .padding(10)
+                .push(Text::new("some settings couldn't be used:").size(16).color(style::PINK));
+            for problem in self.config_problems.iter() {
+                problems = problems.push(Text::new(problem).size(14).color(style::text_color()));
+            }
+            problems = problems.push(
+                Button::new(&mut self.dismiss_problems_btn_state, Text::new("dismiss"))
+                .padding(5)
+                .style(style::StyledButton::primary())
+                .on_press(Message::DismissConfigProblems)
+            );
+            content = content.push(problems);
+        }
+        if let Some(onboarding) = self.onboarding.as_mut() {
+            content = content.push(
+                Scrollable::new(&mut self.body_scroll)
+                    .width(Length::Fill)
+                    .push(onboarding.view().map(|message| Message::OnboardingMessage(message)))
+            );
+            return Container::new(content).style(style::Surface).width(Length::Fill).height(Length::Fill).into();
+        }
         content = content.push(self.header.view().map(|ms| Message::HeaderMessage(ms)));
         let sidebar = Scrollable::new(&mut self.sidebar_scroll).width(Length::FillPortion(3))
         .push(
@@ -96,13 +285,19 @@ impl Application for Mark {
         )
         .push(Space::new(Length::Fill, Length::Units(1500)))
         ;
-        let body = Scrollable::new(&mut self.body_scroll)
-            .width(Length::FillPortion(6))
-            .push(
-                self.mark_components
-                    .view()
-                    .map(|message| Message::MCEvent(message)),
-            );
+        let body_content = match self.body_view {
+            BodyView::Health => self.health.view().map(|message| Message::HealthMessage(message)),
+            BodyView::Diagnostics => self.diagnostics.view().map(|message| Message::DiagnosticsMessage(message)),
+            BodyView::Marks => self.mark_components
+                .view()
+                .map(|message| Message::MCEvent(message))
+        };
+        let body = ScrollWatch::new(
+            Scrollable::new(&mut self.body_scroll)
+                .width(Length::FillPortion(6))
+                .push(body_content),
+            Message::BodyScrolled
+        );
         content = content.push(
             Row::new()
                 .push(
@@ -114,12 +309,34 @@ impl Application for Mark {
         Container::new(content).style(style::Surface).width(Length::Fill).into()
     }
     fn subscription(&self) -> Subscription<Message> {
-        match &self.state {
-            State::LoadItems(i, cat, search) => {
-                subscriptions::marks::load_marks(i, cat.to_owned(), search.to_owned()).map(|mark| Message::MCEvent(MCMessage::LoadMark(mark)))
+        let marks = match &self.state {
+            State::LoadItems(i, query) => {
+                subscriptions::marks::load_marks(i, query.to_owned()).map(|mark| Message::MCEvent(MCMessage::LoadMark(mark)))
             },
             _ => Subscription::none()
-        }
+        };
+        // the sync runs inside this one, it ends with Synced
+        let sync = match self.header.running_sync() {
+            Some(run) => subscriptions::sync::sync_library(run).map(|event| match event {
+                SyncEvent::Progress(progress) => Message::HeaderMessage(HeaderMessage::SyncProgress(progress)),
+                SyncEvent::MarkSaved(mark, new) => Message::MCEvent(MCMessage::MarkSynced(mark, new)),
+                SyncEvent::Finished(errors) => Message::Synced(errors),
+            }),
+            None => Subscription::none()
+        };
+        let toast_timer = if self.header.toast_visible() {
+            iced::time::every(Duration::from_secs(1)).map(|now| Message::HeaderMessage(HeaderMessage::Tick(now)))
+        } else {
+            Subscription::none()
+        };
+        Subscription::batch(vec![
+            marks,
+            sync,
+            toast_timer,
+            subscriptions::scroll::scroll_events().map(Message::BodyScroll),
+            subscriptions::watcher::watch_bookmarks(self.header.watch_paths())
+                .map(|_| Message::HeaderMessage(HeaderMessage::BookmarksChanged))
+        ])
     }
 
     fn mode(&self) -> iced::window::Mode {
@@ -127,7 +344,7 @@ impl Application for Mark {
     }
 
     fn background_color(&self) -> iced::Color {
-        iced::Color::WHITE
+        style::surface_color()
     }
 
     fn scale_factor(&self) -> f64 {
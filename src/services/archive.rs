@@ -0,0 +1,190 @@
+use crate::config;
+use crate::user_config;
+use crate::utils::{Error, ErrorKind};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+// bigger images and stylesheets are left pointing to the web
+const MAX_RESOURCE_BYTES: usize = 5 * 1024 * 1024;
+
+static SCRIPT_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<script\b.*?</script\s*>|<noscript\b.*?</noscript\s*>").unwrap());
+static LINK_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<link\b[^>]*>").unwrap());
+static IMG_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<img\b[^>]*>").unwrap());
+static HEAD_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<head\b[^>]*>").unwrap());
+static CSS_URL: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(?i)url\(\s*['"]?([^'")]+)['"]?\s*\)"#).unwrap());
+static TAG_ATTR: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?is)\s([^\s"'<>/=]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#).unwrap());
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ArchiveSettings {
+    /// the folders whose pages are archived, their subfolders included
+    pub categories: HashSet<String>,
+}
+
+pub fn load_archive_settings() -> ArchiveSettings {
+    fs::read_to_string(config::get_archive_settings_file_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_archive_settings(settings: &ArchiveSettings) -> Result<(), Error> {
+    let content = serde_json::to_string_pretty(settings).unwrap_or_default();
+    fs::write(config::get_archive_settings_file_path(), content)?;
+    Ok(())
+}
+
+/// where the archive of a mark is kept
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedCopy {
+    /// relative to the archives dir
+    pub file: String,
+    pub size: u64,
+    /// unix seconds
+    pub date: i64,
+}
+
+impl ArchivedCopy {
+    pub fn path(&self) -> PathBuf {
+        config::get_archives_dir().join(&self.file)
+    }
+    pub fn remove(&self) {
+        fs::remove_file(self.path()).ok();
+    }
+}
+
+/// the disk space all archived pages take, it walks the whole archives dir
+pub async fn storage_used() -> u64 {
+    fs::read_dir(config::get_archives_dir())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| entry.metadata().ok())
+                .filter(|metadata| metadata.is_file())
+                .map(|metadata| metadata.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// the value of an attribute inside a single tag
+fn attr_value(tag: &str, name: &str) -> Option<String> {
+    let captures = TAG_ATTR.captures_iter(tag).find(|captures| captures[1].eq_ignore_ascii_case(name))?;
+    captures.get(2).or_else(|| captures.get(3)).or_else(|| captures.get(4)).map(|val| val.as_str().replace("&amp;", "&"))
+}
+
+fn remove_attr(tag: &str, name: &str) -> String {
+    TAG_ATTR
+        .replace_all(tag, |captures: &Captures| {
+            if captures[1].eq_ignore_ascii_case(name) {
+                String::new()
+            } else {
+                captures[0].to_string()
+            }
+        })
+        .to_string()
+}
+
+async fn fetch_resource(client: &reqwest::Client, url: &Url) -> Option<(String, Vec<u8>)> {
+    let response = client.get(url.as_str()).send().await.ok()?.error_for_status().ok()?;
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|val| val.to_str().ok())
+        .map(|val| val.split(';').next().unwrap_or_default().trim().to_string())
+        .unwrap_or_else(|| String::from("application/octet-stream"));
+    let bytes = response.bytes().await.ok()?;
+    if bytes.len() > MAX_RESOURCE_BYTES {
+        return None;
+    }
+    Some((content_type, bytes.to_vec()))
+}
+
+async fn data_uri(client: &reqwest::Client, url: &Url) -> Option<String> {
+    let (content_type, bytes) = fetch_resource(client, url).await?;
+    Some(format!("data:{};base64,{}", content_type, base64::encode(bytes)))
+}
+
+/// a stylesheet with the images and fonts it points to inlined, relative to where it was loaded from
+async fn inline_css(client: &reqwest::Client, css: &str, base: &Url) -> String {
+    let mut inlined = Vec::new();
+    for captures in CSS_URL.captures_iter(css) {
+        let target = &captures[1];
+        if target.starts_with("data:") {
+            continue;
+        }
+        if let Ok(url) = base.join(target) {
+            if let Some(uri) = data_uri(client, &url).await {
+                inlined.push((target.to_string(), uri));
+            }
+        }
+    }
+    CSS_URL
+        .replace_all(css, |captures: &Captures| {
+            match inlined.iter().find(|(target, _)| target == &captures[1]) {
+                Some((_, uri)) => format!("url(\"{}\")", uri),
+                None => captures[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+/// saves the page as one html file that opens offline: scripts are dropped, stylesheets
+/// and images are inlined, and a base tag keeps the remaining links pointing to the site.
+pub async fn archive_page(mark_id: &str, link: &str) -> Result<ArchivedCopy, Error> {
+    let base = Url::parse(link)
+        .map_err(|error| Error::new(ErrorKind::Parse, error.to_string()).with_url(link))?;
+    let client = reqwest::Client::builder().timeout(Duration::from_secs(user_config::get().archive_timeout_secs)).build()?;
+    let page = client.get(link).send().await?.error_for_status()?.text().await?;
+    let page = SCRIPT_TAG.replace_all(&page, "").to_string();
+
+    let mut stylesheets = Vec::new();
+    for tag in LINK_TAG.find_iter(&page).map(|tag| tag.as_str().to_string()) {
+        let is_stylesheet = attr_value(&tag, "rel").map_or(false, |rel| rel.to_lowercase().contains("stylesheet"));
+        let href = attr_value(&tag, "href").and_then(|href| base.join(&href).ok());
+        if let (true, Some(url)) = (is_stylesheet, href) {
+            if let Some((_, bytes)) = fetch_resource(&client, &url).await {
+                let css = inline_css(&client, &String::from_utf8_lossy(&bytes), &url).await;
+                stylesheets.push((tag, format!("<style>\n{}\n</style>", css)));
+            }
+        }
+    }
+    let mut images = Vec::new();
+    for tag in IMG_TAG.find_iter(&page).map(|tag| tag.as_str().to_string()) {
+        let src = attr_value(&tag, "src").filter(|src| !src.starts_with("data:"));
+        if let Some(url) = src.and_then(|src| base.join(&src).ok()) {
+            if let Some(uri) = data_uri(&client, &url).await {
+                // srcset would win over the inlined src
+                let tag_without_src = remove_attr(&remove_attr(&tag, "srcset"), "src");
+                // the tag starts with "<img" in whatever case
+                let inlined = format!("<img src=\"{}\"{}", uri, &tag_without_src[4..]);
+                images.push((tag, inlined));
+            }
+        }
+    }
+
+    let mut archived = page;
+    for (tag, replacement) in stylesheets.iter().chain(images.iter()) {
+        archived = archived.replacen(tag.as_str(), replacement, 1);
+    }
+    let base_tag = format!("<base href=\"{}\">", base.as_str().replace('"', "&quot;"));
+    archived = match HEAD_TAG.find(&archived) {
+        Some(head) => format!("{}{}{}", &archived[..head.end()], base_tag, &archived[head.end()..]),
+        None => format!("{}{}", base_tag, archived),
+    };
+
+    let copy = ArchivedCopy {
+        file: format!("{}.html", mark_id),
+        size: archived.len() as u64,
+        date: chrono::Utc::now().timestamp(),
+    };
+    fs::create_dir_all(config::get_archives_dir())?;
+    fs::write(copy.path(), archived.as_bytes())?;
+    Ok(copy)
+}
@@ -1,44 +1,92 @@
-use crate::utils::Error;
-use futures::future;
-use jfs::Store;
+use crate::utils::{self, Error, ErrorKind};
+use futures::StreamExt;
 use link_preview;
+use log::{debug, error, info, warn};
 use once_cell::sync::Lazy;
-use regex::RegexBuilder;
+use regex::Regex;
+use rusqlite::{functions::FunctionFlags, params_from_iter, types::Value};
+use scraper::Html;
 use reqwest;
 use serde::{Deserialize, Serialize};
 use serde_json;
-use std::collections::BTreeMap;
-use std::ffi::OsStr;
+use std::cmp::Ordering;
+use std::fmt;
 use std::fs;
 use std::fs::OpenOptions;
 use std::io::BufReader;
 use std::io::Write;
-use std::path::{Path, PathBuf};
+use std::panic::AssertUnwindSafe;
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Mutex;
 use std::sync::MutexGuard;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::time::timeout;
 use crate::config;
+use crate::user_config;
+use crate::services::netscape::{self, NetscapeSource};
+use crate::services::source::{self, BookmarkSource, SourceError, SourceInfo, SourceSettings};
+use crate::services::mark_store::{self, MarkStore, SNIPPET_END, SNIPPET_START};
+use crate::services::archive::{self, ArchiveSettings, ArchivedCopy};
+use crate::services::health::{self, HealthReport, LinkFixes, LinkHealth};
+use crate::services::page_meta::{self, PageMeta};
+use crate::services::query;
+use crate::services::readability;
+use crate::services::sync::{self, BookmarkDiff, SyncReporter, SyncStage};
 
 static BOOKMARK_API: Lazy<Mutex<BookmarkApi>> = Lazy::new(|| Mutex::new(BookmarkApi::new()));
 
+pub const CATEGORY_PATH_SEPARATOR: &str = " › ";
+// enough page text to find an article by what it talks about without bloating the index
+const MAX_PAGE_TEXT_WORDS: usize = 5000;
+// a bookmark opened this many days ago gets half the boost of one opened today
+const RECENCY_BOOST_DAYS: f64 = 30.0;
+// how many links are checked at the same time
+const HEALTH_CHECK_CONCURRENCY: usize = 16;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BookmarkFile {
-    checksum: String,
-    roots: BookmarksRoot,
+    pub checksum: String,
+    pub roots: BookmarksRoot,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BookmarksRoot {
-    bookmark_bar: BookmarkBar,
-    other: BookmarkBar,
-    synced: BookmarkBar,
+    pub bookmark_bar: BookmarkBar,
+    pub other: BookmarkBar,
+    pub synced: BookmarkBar,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BookmarkBar {
-    children: Option<Vec<BookmarksItem>>,
+    #[serde(default)]
+    pub id: Option<String>,
+    /// roots without a name (imported html files) have their children placed at the top level
+    #[serde(default)]
+    pub name: Option<String>,
+    pub children: Option<Vec<BookmarksItem>>,
+}
+
+impl BookmarkBar {
+    fn into_items(mut self) -> Vec<BookmarksItem> {
+        let children = self.children.take().unwrap_or_default();
+        match self.name {
+            Some(name) => vec![BookmarksItem {
+                date_added: String::new(),
+                date_last_used: None,
+                guid: String::new(),
+                id: self.id.unwrap_or(format!("root-{}", SourceInfo::slug(&name))),
+                name,
+                r#type: BookmarkType::folder,
+                url: None,
+                children: Some(children),
+                icon: None,
+                tags: Vec::new(),
+                source: None,
+            }],
+            None => children,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -50,75 +98,123 @@ pub enum BookmarkType {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BookmarksItem {
     pub date_added: String,
+    #[serde(default)]
+    pub date_last_used: Option<String>,
     pub guid: String,
     pub id: String,
     pub name: String,
     pub r#type: BookmarkType,
     pub url: Option<String>,
     pub children: Option<Vec<BookmarksItem>>,
-}
-#[derive(PartialEq)]
-pub enum ChildLevel {
-    Outer,
-    Inner,
+    #[serde(skip)]
+    pub icon: Option<String>,
+    #[serde(skip)]
+    pub tags: Vec<String>,
+    #[serde(skip)]
+    pub source: Option<SourceInfo>,
 }
 impl BookmarksItem {
-    pub fn flatten(
-        nested: Vec<BookmarksItem>,
-        acc_arr: &mut Vec<BookmarksItem>,
-        level: ChildLevel,
-    ) -> Vec<BookmarksItem> {
-        // loop throw the parent array
-        nested.into_iter().for_each(|item| {
-            // check if the item has children
-            if item.r#type == BookmarkType::folder && item.children.is_some() {
-                // check if the child has sub children
-                let found_sub_child = item
+    /// prefixes the ids with the source id so bookmarks from different browsers never collide
+    pub fn tag_source(items: Vec<BookmarksItem>, source: &SourceInfo) -> Vec<BookmarksItem> {
+        items
+            .into_iter()
+            .map(|mut item| {
+                item.id = format!("{}_{}", source.id, item.id);
+                item.source = Some(source.clone());
+                item.children = item
                     .children
-                    .as_ref()
-                    .unwrap()
-                    .into_iter()
-                    .find(|child| child.children.is_some());
-                if found_sub_child.is_some() {
-                    // in this case do recursive to get the inner one
-                    BookmarksItem::flatten(
-                        item.children.as_ref().unwrap().to_vec(),
-                        acc_arr.as_mut(),
-                        ChildLevel::Inner,
-                    );
-                    let mut cloned_item = item.clone();
-                    // reset the children to get children with url type only not folders to avoid children duplication
-                    cloned_item.children = Some(
-                        cloned_item
-                            .children
-                            .unwrap()
-                            .into_iter()
-                            .filter(|child_item| child_item.r#type == BookmarkType::url)
-                            .collect(),
-                    );
-                    // push the cloned item into the accumulated array
-                    acc_arr.push(cloned_item);
-                } else {
-                    // push the cloned item into the accumulated array
-                    acc_arr.push(item.clone());
+                    .map(|children| BookmarksItem::tag_source(children, source));
+                item
+            })
+            .collect()
+    }
+    /// drops the bookmarks removed from the link health view and repoints the relinked ones
+    pub fn apply_fixes(items: Vec<BookmarksItem>, fixes: &LinkFixes) -> Vec<BookmarksItem> {
+        items
+            .into_iter()
+            .filter(|item| !fixes.removed.contains(&item.id))
+            .map(|mut item| {
+                if let Some(url) = fixes.relinked.get(&item.id) {
+                    item.url = Some(url.clone());
+                }
+                item.children = item
+                    .children
+                    .map(|children| BookmarksItem::apply_fixes(children, fixes));
+                item
+            })
+            .collect()
+    }
+    /// walks the folders in pre-order, so a parent category always comes before its subfolders
+    pub fn collect_categories(
+        nested: &Vec<BookmarksItem>,
+        parent: Option<&BookmarkCategory>,
+        acc_arr: &mut Vec<BookmarkCategory>,
+    ) {
+        nested
+            .iter()
+            .filter(|item| item.r#type == BookmarkType::folder)
+            .for_each(|item| {
+                let path = match parent {
+                    Some(parent) => format!("{}{}{}", parent.path, CATEGORY_PATH_SEPARATOR, item.name),
+                    None => item.name.clone(),
+                };
+                let category = BookmarkCategory {
+                    name: item.name.clone(),
+                    id: item.id.clone(),
+                    source: item.source.clone(),
+                    parent_id: parent.map(|parent| parent.id.clone()),
+                    path,
+                };
+                acc_arr.push(category.clone());
+                if let Some(children) = &item.children {
+                    BookmarksItem::collect_categories(children, Some(&category), acc_arr);
+                }
+            });
+    }
+    /// every url bookmark with the category it is synced under, top level bookmarks
+    /// go in the given root category and the rest in their folder's category
+    pub fn collect_marks(
+        nested: &Vec<BookmarksItem>,
+        category: &BookmarkCategory,
+        categories: &Vec<BookmarkCategory>,
+        acc_arr: &mut Vec<(BookmarksItem, BookmarkCategory)>,
+    ) {
+        nested.iter().for_each(|item| match item.r#type {
+            BookmarkType::url => acc_arr.push((item.clone(), category.clone())),
+            BookmarkType::folder => {
+                let folder_category = categories.iter().find(|cat| cat.id == item.id);
+                if let (Some(folder_category), Some(children)) = (folder_category, &item.children) {
+                    BookmarksItem::collect_marks(children, folder_category, categories, acc_arr);
                 }
-            }
-            // check if the item in the top level, no sub children and with url type
-            else if item.r#type == BookmarkType::url
-                && item.children.is_none()
-                && level == ChildLevel::Outer
-            {
-                // push the cloned item into the accumulated array
-                acc_arr.push(item.clone());
             }
         });
-        acc_arr.to_vec()
+    }
+    pub fn find_folder<'a>(nested: &'a Vec<BookmarksItem>, folder_id: &str) -> Option<&'a BookmarksItem> {
+        nested
+            .iter()
+            .filter(|item| item.r#type == BookmarkType::folder)
+            .find_map(|item| {
+                if item.id == folder_id {
+                    Some(item)
+                } else {
+                    item.children
+                        .as_ref()
+                        .and_then(|children| BookmarksItem::find_folder(children, folder_id))
+                }
+            })
     }
 }
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct BookmarkCategory {
     pub name: String,
     pub id: String,
+    #[serde(default)]
+    pub source: Option<SourceInfo>,
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    /// the folder names from the root down to this folder, e.g. "Bookmarks bar › Dev › Rust"
+    #[serde(default)]
+    pub path: String,
 }
 
 impl BookmarkCategory{
@@ -126,380 +222,842 @@ impl BookmarkCategory{
         BookmarkCategory{
             id: String::from("non categorized"),
             name: String::from("non categorized"),
+            source: None,
+            parent_id: None,
+            path: String::from("non categorized"),
+        }
+    }
+    /// marks cached before folder paths existed only know their folder name
+    pub fn display_path(&self) -> &str {
+        if self.path.is_empty() {
+            &self.name
+        } else {
+            &self.path
         }
     }
 }
 // #[derive(Debug)]
 pub struct BookmarkApi {
-    bookmark_file: Option<BookmarkFile>,
+    sources: Vec<Box<dyn BookmarkSource>>,
+    source_settings: SourceSettings,
+    archive_settings: ArchiveSettings,
+    link_fixes: LinkFixes,
+    bookmark_files: Option<Vec<(SourceInfo, BookmarkFile)>>,
+    // the visible sources whose bookmarks couldn't be read the last time
+    read_errors: Vec<(SourceInfo, SourceError)>,
     bookmarks: Option<Vec<BookmarksItem>>,
     categories: Vec<BookmarkCategory>,
     // storage: Option<File>,
-    db: Store,
+    db: Option<MarkStore>,
+    // why the cache file couldn't be opened, reported by the next sync
+    db_error: Option<Error>,
 }
 impl BookmarkApi {
-    fn bootstrap() -> Store {
-        fs::create_dir_all(
-            Path::new(
-                dirs::cache_dir()
-                    .unwrap_or(Path::new("").to_path_buf())
-                    .as_path(),
-            )
-            .join(config::CACHE_MAIN_DIR),
-        )
-        .ok();
-        let dir_path = Path::new(dirs::cache_dir().unwrap().as_path()).join(config::CACHE_IMG_PATH);
+    fn bootstrap() -> Result<MarkStore, Error> {
+        let dir_path = config::get_full_img_cache_path();
         fs::create_dir_all(&dir_path).ok();
-        let mut cfg = jfs::Config::default();
-        cfg.single = true;
-        cfg.pretty = true;
-        // match  Path::new(config::get_cache_file_path().as_path()).is_file() {
-        //     true => (),
-        //     false => {
-        //         let mut file = OpenOptions::new().create(true).write(true).open(config::get_cache_file_path()).expect("Couldn't open cache file");
-        //         file.write("{}".as_bytes()).expect("Couldn't open cache file");
-        //     },
-        // };
-        Store::new_with_cfg(
-            config::get_cache_file_path(),
-            cfg,
-        )
-        .unwrap()
+        MarkStore::open()
     }
     pub fn new() -> Self {
         BookmarkApi {
-            bookmark_file: None,
+            sources: source::discover_sources(),
+            source_settings: source::load_source_settings(),
+            archive_settings: archive::load_archive_settings(),
+            link_fixes: health::load_link_fixes(),
+            bookmark_files: None,
+            read_errors: Vec::new(),
             bookmarks: None,
             categories: Vec::new(),
-            db: Self::bootstrap(),
+            db: None,
+            db_error: None,
         }
     }
     pub fn init<'a>() -> MutexGuard<'a, Self> {
         BOOKMARK_API.lock().unwrap()
     }
+    /// the marks cache, opened (and migrated from the old json files) on first use.
+    /// a cache that can't be opened leaves the marks in memory for this run and the error is kept
+    fn db(&mut self) -> &MarkStore {
+        if self.db.is_none() {
+            let store = Self::bootstrap().unwrap_or_else(|error| {
+                error!("the marks cache can't be opened, nothing is kept after this run error=\"{}\"", error);
+                self.db_error = Some(error);
+                MarkStore::open_in_memory().expect("sqlite can't open an in-memory database")
+            });
+            self.db = Some(store);
+        }
+        self.db.as_ref().unwrap()
+    }
+    fn visible_source_ids(&self) -> Vec<String> {
+        self.get_visible_sources().into_iter().map(|(info, _)| info.id).collect()
+    }
+    /// the sources whose bookmarks are shown: the picked profile or every enabled source
+    fn is_visible(&self, info: &SourceInfo) -> bool {
+        match &self.source_settings.active {
+            Some(active) => active == &info.id,
+            None => source::is_enabled(&self.source_settings.enabled, info),
+        }
+    }
     pub fn read_bookmarks_from_file(&mut self) -> &Self {
-        let file = fs::File::open(config::get_bookmarks_path()).unwrap();
-        let reader = BufReader::new(file);
-        let book_marks_file: BookmarkFile = serde_json::from_reader(reader).unwrap();
-        self.bookmark_file = Some(book_marks_file);
+        let mut bookmark_files: Vec<(SourceInfo, BookmarkFile)> = Vec::new();
+        let mut read_errors: Vec<(SourceInfo, SourceError)> = Vec::new();
+        for bookmark_source in self.sources.iter() {
+            let info = bookmark_source.info();
+            if !self.is_visible(&info) {
+                continue;
+            }
+            match bookmark_source.read_bookmark_file() {
+                Ok(bookmark_file) => bookmark_files.push((info, bookmark_file)),
+                Err(error) => read_errors.push((info, error)),
+            }
+        }
+        self.bookmark_files = Some(bookmark_files);
+        self.read_errors = read_errors;
         self
     }
-    pub fn get_bookmark_file(&mut self) -> &mut Option<BookmarkFile> {
-        if self.bookmark_file.is_none() {
+    /// the sources whose file was missing or broken at the last sync
+    pub fn get_read_errors(&self) -> Vec<(SourceInfo, SourceError)> {
+        self.read_errors.clone()
+    }
+    /// the first-run screen is shown until it's closed, or right away when no source was found
+    pub fn needs_onboarding(&self) -> bool {
+        self.sources.is_empty() || (!self.source_settings.onboarded && self.source_settings.enabled.is_none())
+    }
+    pub fn finish_onboarding(&mut self) {
+        self.source_settings.onboarded = true;
+        source::save_source_settings(&self.source_settings).ok();
+    }
+    /// looks for the browsers again and tries to read every source found, for the first-run screen
+    pub fn detect_sources(&mut self) -> Vec<(SourceInfo, bool, Option<SourceError>)> {
+        self.sources = source::discover_sources();
+        self.bookmark_files = None;
+        self.bookmarks = None;
+        self.categories = Vec::new();
+        self.sources
+            .iter()
+            .map(|bookmark_source| {
+                let info = bookmark_source.info();
+                let enabled = source::is_enabled(&self.source_settings.enabled, &info);
+                (info, enabled, bookmark_source.read_bookmark_file().err())
+            })
+            .collect()
+    }
+    /// adds a bookmarks file from a place the browsers aren't looked for in, html exports are imported
+    pub async fn pick_bookmarks_file() -> Result<(), Error> {
+        // chromium's Bookmarks file has no extension, so nothing is filtered out
+        let file = rfd::AsyncFileDialog::new().pick_file().await;
+        if let Some(file) = file {
+            let path = file.path().to_path_buf();
+            let info = source::picked_file_source(path.clone()).info();
+            let mut bookmark_api = Self::init();
+            if !bookmark_api.source_settings.files.contains(&path) {
+                bookmark_api.source_settings.files.push(path);
+            }
+            source::save_source_settings(&bookmark_api.source_settings)?;
+            bookmark_api.sources = source::discover_sources();
+            bookmark_api.set_source_enabled(&info.id, true);
+        }
+        Ok(())
+    }
+    pub fn get_bookmark_files(&mut self) -> &mut Vec<(SourceInfo, BookmarkFile)> {
+        if self.bookmark_files.is_none() {
             self.read_bookmarks_from_file();
         }
-        &mut self.bookmark_file
+        self.bookmark_files.as_mut().unwrap()
+    }
+    pub fn get_sources(&self) -> Vec<(SourceInfo, bool)> {
+        self.sources
+            .iter()
+            .map(|bookmark_source| {
+                let info = bookmark_source.info();
+                let enabled = source::is_enabled(&self.source_settings.enabled, &info);
+                (info, enabled)
+            })
+            .collect()
+    }
+    /// the sources currently making up the library, used by the sidebar source filter
+    pub fn get_visible_sources(&self) -> Vec<(SourceInfo, bool)> {
+        self.get_sources()
+            .into_iter()
+            .filter(|(info, _)| self.is_visible(info))
+            .collect()
+    }
+    pub fn set_source_enabled(&mut self, source_id: &str, enabled: bool) {
+        let mut ids: Vec<String> = self
+            .get_sources()
+            .into_iter()
+            .filter(|(info, is_enabled)| *is_enabled && info.id != source_id)
+            .map(|(info, _)| info.id)
+            .collect();
+        if enabled {
+            ids.push(source_id.to_string());
+        }
+        self.source_settings.enabled = Some(ids);
+        source::save_source_settings(&self.source_settings).ok();
+    }
+    /// the bookmark files of the sources making up the library
+    pub fn get_watch_paths(&self) -> Vec<PathBuf> {
+        self.sources
+            .iter()
+            .filter(|bookmark_source| self.is_visible(&bookmark_source.info()))
+            .flat_map(|bookmark_source| bookmark_source.watch_paths())
+            .collect()
+    }
+    pub fn get_active_profile(&self) -> Option<SourceInfo> {
+        let active = self.source_settings.active.as_ref()?;
+        self.get_sources()
+            .into_iter()
+            .map(|(info, _)| info)
+            .find(|info| &info.id == active)
+    }
+    pub fn set_active_profile(&mut self, source_id: Option<String>) {
+        self.source_settings.active = source_id;
+        source::save_source_settings(&self.source_settings).ok();
     }
     pub fn get_raw_bookmarks(&mut self) -> &Vec<BookmarksItem> {
         match self.bookmarks {
             None => {
                 let mut new_bookmarks: Vec<BookmarksItem> = Vec::new();
-                new_bookmarks.append(
-                    self.get_bookmark_file()
-                        .as_mut()
-                        .unwrap()
-                        .roots
-                        .bookmark_bar
-                        .children
-                        .as_mut()
-                        .unwrap(),
-                );
-                new_bookmarks.append(
-                    self.get_bookmark_file()
-                        .as_mut()
-                        .unwrap()
-                        .roots
-                        .other
-                        .children
-                        .as_mut()
-                        .unwrap(),
-                );
-                new_bookmarks.append(
-                    self.get_bookmark_file()
-                        .as_mut()
-                        .unwrap()
-                        .roots
-                        .synced
-                        .children
-                        .as_mut()
-                        .unwrap(),
-                );
+                for (info, bookmark_file) in self.get_bookmark_files().iter_mut() {
+                    let roots = bookmark_file.roots.clone();
+                    let mut source_bookmarks: Vec<BookmarksItem> = Vec::new();
+                    source_bookmarks.append(&mut roots.bookmark_bar.into_items());
+                    source_bookmarks.append(&mut roots.other.into_items());
+                    source_bookmarks.append(&mut roots.synced.into_items());
+                    new_bookmarks.append(&mut BookmarksItem::tag_source(source_bookmarks, info));
+                }
+                let new_bookmarks = BookmarksItem::apply_fixes(new_bookmarks, &self.link_fixes);
                 self.bookmarks = Some(new_bookmarks);
             }
             Some(_) => (),
         };
-        self.bookmarks = Some(BookmarksItem::flatten(
-            self.bookmarks.as_ref().unwrap_or(&Vec::new()).to_vec(),
-            Vec::new().as_mut(),
-            ChildLevel::Outer,
-        ));
         self.bookmarks.as_ref().unwrap()
     }
     pub fn get_categories(&mut self) -> &Vec<BookmarkCategory> {
         if self.categories.len() == 0 {
-            let bookmarks = self.get_raw_bookmarks();
-            let mut categories: Vec<BookmarkCategory> = bookmarks
-                .into_iter()
-                .filter_map(|bookmark| {
-                    if bookmark.r#type == BookmarkType::folder {
-                        Some(BookmarkCategory {
-                            name: bookmark.name.clone(),
-                            id: bookmark.id.clone(),
-                        })
-                    } else {
-                        None
-                    }
-                })
-                .collect();
+            let mut categories: Vec<BookmarkCategory> = Vec::new();
+            BookmarksItem::collect_categories(self.get_raw_bookmarks(), None, &mut categories);
             self.categories.push(BookmarkCategory::default());
             self.categories.append(categories.as_mut())
         }
         self.categories.as_ref()
     }
     pub fn get_category(&mut self, cat_id: &str) -> Option<&BookmarkCategory> {
-        if self.categories.len() == 0 {
-            let bookmarks = self.get_raw_bookmarks();
-            let mut categories: Vec<BookmarkCategory> = bookmarks
-                .into_iter()
-                .filter_map(|bookmark| {
-                    if bookmark.r#type == BookmarkType::folder {
-                        Some(BookmarkCategory {
-                            name: bookmark.name.clone(),
-                            id: bookmark.id.clone(),
-                        })
-                    } else {
-                        None
-                    }
-                })
-                .collect();
-            self.categories.append(categories.as_mut())
-        }
         let cat = self.get_categories().into_iter().find(|item| item.id == cat_id);
         cat
     }
-    pub fn filter_marks_by_category(&mut self, cat_id: Option<&str>) -> Vec<BookmarksItem> {
-        let default_list = &Vec::new();
-        let category = if cat_id.is_some() {
-            Some(self
-                .bookmarks
-                .as_ref()
-                .unwrap_or(default_list)
-                .into_iter()
-                .find(|bookmark| bookmark.id.eq(&cat_id.unwrap())))
+    /// the id of the category followed by the ids of all its subfolders
+    pub fn get_category_tree_ids(&mut self, cat_id: &str) -> Vec<String> {
+        let mut ids: Vec<String> = vec![cat_id.to_string()];
+        self.get_categories().iter().for_each(|category| {
+            if category.parent_id.as_ref().map_or(false, |parent_id| ids.contains(parent_id)) {
+                ids.push(category.id.clone());
+            }
+        });
+        ids
+    }
+    /// whether the pages of this folder were picked to be archived
+    pub fn is_category_archived(&self, cat_id: &str) -> bool {
+        self.archive_settings.categories.contains(cat_id)
+    }
+    /// the picked folders and all their subfolders
+    fn archived_category_ids(&mut self) -> Vec<String> {
+        let mut ids: Vec<String> = Vec::new();
+        for cat_id in self.archive_settings.categories.clone().iter() {
+            ids.extend(self.get_category_tree_ids(cat_id));
+        }
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+    /// turning a folder off drops the archives nothing else asks for, turning it on
+    /// leaves the archiving to the next sync
+    pub fn set_category_archived(&mut self, cat_id: &str, archived: bool) {
+        let before = self.archived_category_ids();
+        if archived {
+            self.archive_settings.categories.insert(cat_id.to_string());
         } else {
-            None
+            self.archive_settings.categories.remove(cat_id);
+        }
+        archive::save_archive_settings(&self.archive_settings).ok();
+        let after = self.archived_category_ids();
+        let dropped: Vec<String> = before.into_iter().filter(|id| !after.contains(id)).collect();
+        if dropped.is_empty() {
+            return;
+        }
+        let db = self.db();
+        for (id, mut mark) in db.marks_in_categories(&dropped).unwrap_or_default().into_iter() {
+            if let Some(copy) = mark.archive.take() {
+                copy.remove();
+                db.save(&id, &mark).ok();
+            }
+        }
+    }
+    /// the direct url children of a folder, or the top level urls when no folder is given
+    pub fn filter_marks_by_category(&mut self, cat_id: Option<&str>) -> Vec<BookmarksItem> {
+        let bookmarks = self.get_raw_bookmarks();
+        let children: Vec<BookmarksItem> = match cat_id {
+            Some(cat_id) => BookmarksItem::find_folder(bookmarks, cat_id)
+                .and_then(|folder| folder.children.clone())
+                .unwrap_or_default(),
+            None => bookmarks.to_vec(),
         };
-        if category.is_some() {
-            if category.unwrap().is_some() {
-                category
-                    .unwrap()
-                    .unwrap()
-                    .children
-                    .as_ref()
-                    .unwrap_or(&Vec::new())
-                    .to_vec()
-            } else {
-                Vec::new()
+        children
+            .into_iter()
+            .filter(|item| item.r#type == BookmarkType::url)
+            .collect()
+    }
+    /// only the sources whose bookmarks file changed since the last sync are diffed against their
+    /// cached marks, then new and re-pointed bookmarks are scraped and deleted ones dropped
+    /// returns what went wrong on the way, the sync itself goes on past every failure.
+    /// the reporter gets the progress and every mark as soon as it's saved
+    pub async fn sync_all(reporter: &SyncReporter) -> Vec<Error> {
+        let started = Instant::now();
+        Self::reset_bookmarks();
+        let (diffs, db_error): (Vec<(SourceInfo, String, BookmarkDiff)>, Option<Error>) = {
+            let mut bookmark_api = Self::init();
+            let diffs = bookmark_api.diff_sources();
+            (diffs, bookmark_api.db_error.take())
+        };
+        info!("sync started changed_sources={}", diffs.len());
+        let to_scrape: usize = diffs.iter().map(|(_, _, diff)| diff.added.len() + diff.url_changed.len()).sum();
+        reporter.stage(SyncStage::Scraping, to_scrape);
+        let mut errors: Vec<Error> = db_error.into_iter().collect();
+        let mut sync_state = sync::load_sync_state();
+        for (info, checksum, diff) in diffs.into_iter() {
+            let (complete, diff_errors) = Self::apply_diff(&info, diff, reporter).await;
+            // a failed scrape keeps the old checksum so the bookmark is tried again next time
+            if complete && !checksum.is_empty() {
+                sync_state.checksums.insert(info.id.clone(), checksum);
             }
-        } else {
-            let res: Vec<BookmarksItem> = self.bookmarks.as_ref().unwrap_or(&Vec::new()).into_iter()
-            .map(|item| item.clone())
-            .filter(|item| {
-                &item.r#type == &BookmarkType::url
-            }).collect();
-            res.to_vec()
+            errors.extend(diff_errors);
         }
+        if let Err(error) = sync::save_sync_state(&sync_state) {
+            errors.push(error);
+        }
+        errors.extend(Self::archive_missing(reporter).await);
+        errors.extend(Self::check_links(reporter).await);
+        info!(
+            "sync finished errors={} duration_ms={}",
+            errors.len(),
+            started.elapsed().as_millis()
+        );
+        errors
     }
-    pub async fn sync_all() {
-        let cats = {
-            Self::reset_bookmarks();
+    /// requests the links not checked for a day and records how they answered
+    async fn check_links(reporter: &SyncReporter) -> Vec<Error> {
+        let mut errors: Vec<Error> = Vec::new();
+        let mut to_check: Vec<(String, MarkData)> = Vec::new();
+        {
+            let mut bookmark_api = Self::init();
+            let source_ids = bookmark_api.visible_source_ids();
+            let db = bookmark_api.db();
+            for source_id in source_ids.iter() {
+                match db.marks_for_source(source_id) {
+                    Ok(marks) => to_check.extend(marks.into_iter().filter(|(_, mark)| LinkHealth::needs_check(&mark.health))),
+                    Err(error) => errors.push(error),
+                }
+            }
+        }
+        if !to_check.is_empty() {
+            reporter.stage(SyncStage::CheckingLinks, to_check.len());
+        }
+        let checked: Vec<(String, MarkData, LinkHealth)> = futures::stream::iter(to_check.into_iter())
+            .map(|(id, mark)| async move {
+                reporter.started(&mark.link);
+                let health = health::check(&mark.link).await;
+                reporter.finished(health.is_alive());
+                (id, mark, health)
+            })
+            .buffer_unordered(HEALTH_CHECK_CONCURRENCY)
+            .collect()
+            .await;
+        if !checked.is_empty() {
+            info!("links checked count={}", checked.len());
+        }
+        let mut bookmark_api = Self::init();
+        let db = bookmark_api.db();
+        for (id, mut mark, health) in checked.into_iter() {
+            debug!(
+                "link checked bookmark={} url={} status={} duration_ms={} outcome={}",
+                id,
+                mark.link,
+                health.status.map_or(String::from("none"), |status| status.to_string()),
+                health.elapsed_ms,
+                health.error.as_ref().map_or(String::from("answered"), |error| format!("\"{}\"", error))
+            );
+            mark.link_status = if health.is_alive() { LinkStatus::Alive } else { LinkStatus::Dead };
+            mark.health = Some(health);
+            if let Err(error) = db.save(&id, &mark) {
+                errors.push(error.with_bookmark(&id));
+            }
+        }
+        errors
+    }
+    /// the links of the library that are dead, moved or slow
+    pub async fn load_health_report() -> Result<HealthReport, Error> {
+        let mut bookmark_api = Self::init();
+        let source_ids = bookmark_api.visible_source_ids();
+        let mut marks: Vec<(String, MarkData)> = Vec::new();
+        for source_id in source_ids.iter() {
+            marks.extend(bookmark_api.db().marks_for_source(source_id)?.into_iter());
+        }
+        Ok(HealthReport::new(marks))
+    }
+    /// points every permanently redirected bookmark to where it moved, the next sync scrapes the new urls
+    pub async fn update_redirected_links() -> Result<(), Error> {
+        let report = Self::load_health_report().await?;
+        let mut bookmark_api = Self::init();
+        for entry in report.redirected.iter() {
+            if let Some(moved_to) = &entry.moved_to {
+                bookmark_api.link_fixes.relinked.insert(entry.id.clone(), moved_to.clone());
+            }
+        }
+        bookmark_api.save_link_fixes()
+    }
+    /// drops the bookmarks whose page is not found or whose domain is gone
+    pub async fn remove_dead_bookmarks() -> Result<(), Error> {
+        let report = Self::load_health_report().await?;
+        let mut bookmark_api = Self::init();
+        for entry in report.dead().into_iter() {
+            bookmark_api.link_fixes.removed.insert(entry.id.clone());
+        }
+        bookmark_api.save_link_fixes()
+    }
+    /// the bookmark files didn't change, so every source is diffed again on the next sync
+    fn save_link_fixes(&mut self) -> Result<(), Error> {
+        health::save_link_fixes(&self.link_fixes)?;
+        let mut sync_state = sync::load_sync_state();
+        sync_state.checksums.clear();
+        sync::save_sync_state(&sync_state)
+    }
+    /// archives the marks of the picked folders that have no archived copy yet,
+    /// a page that can't be saved is tried again on the next sync
+    async fn archive_missing(reporter: &SyncReporter) -> Vec<Error> {
+        let missing: Vec<(String, MarkData)> = {
             let mut bookmark_api = Self::init();
-            let cats = bookmark_api.get_categories().clone();
-            cats
+            let category_ids = bookmark_api.archived_category_ids();
+            if category_ids.is_empty() {
+                return Vec::new();
+            }
+            match bookmark_api.db().marks_in_categories(&category_ids) {
+                Ok(marks) => marks.into_iter().filter(|(_, mark)| mark.archive.is_none()).collect(),
+                Err(error) => return vec![error],
+            }
         };
-        future::try_join_all(cats.into_iter().map(Self::sync_category_bookmarks)).await.ok();
-        Self::sync_root_bookmarks().await.ok();
+        if !missing.is_empty() {
+            reporter.stage(SyncStage::Archiving, missing.len());
+        }
+        let archived: Vec<(String, MarkData, Result<ArchivedCopy, Error>)> = futures::stream::iter(missing.into_iter())
+            .map(|(id, mark)| async move {
+                let started = Instant::now();
+                reporter.started(&mark.link);
+                let copy = archive::archive_page(&id, &mark.link).await;
+                reporter.finished(copy.is_ok());
+                match &copy {
+                    Ok(_) => info!(
+                        "page archived bookmark={} url={} duration_ms={} outcome=saved",
+                        id,
+                        mark.link,
+                        started.elapsed().as_millis()
+                    ),
+                    Err(error) => warn!(
+                        "page archive failed bookmark={} url={} duration_ms={} outcome=failed error=\"{}\"",
+                        id,
+                        mark.link,
+                        started.elapsed().as_millis(),
+                        error
+                    ),
+                };
+                (id, mark, copy)
+            })
+            .buffer_unordered(user_config::get().scrape_concurrency)
+            .collect()
+            .await;
+        let mut bookmark_api = Self::init();
+        let db = bookmark_api.db();
+        let mut errors: Vec<Error> = Vec::new();
+        for (id, mut mark, copy) in archived.into_iter() {
+            let saved = copy.and_then(|copy| {
+                mark.archive = Some(copy);
+                db.save(&id, &mark)
+            });
+            if let Err(error) = saved {
+                errors.push(error.with_bookmark(&id).with_url(&mark.link));
+            }
+        }
+        errors
     }
     fn reset_bookmarks() {
         let mut bookmark_api = Self::init();
         bookmark_api.categories = Vec::new();
         bookmark_api.bookmarks = None;
-        bookmark_api.bookmark_file = None;
+        bookmark_api.bookmark_files = None;
+        bookmark_api.sources = source::discover_sources();
         bookmark_api.get_categories();
     }
-    pub async fn sync_root_bookmarks() -> Result<(), Error> {
-        let bookmark_items: Vec<BookmarksItem> = {
-            let mut bookmark_api: MutexGuard<BookmarkApi> = Self::init();
-            bookmark_api.filter_marks_by_category(None)
-        };
-        let cat = BookmarkCategory::default();
-        future::try_join_all(bookmark_items.into_iter().map(|item| Self::sync_bookmark(item, cat.clone())))
-            .await
-            .ok();
-        Ok(())
-    }
-    pub async fn sync_category_bookmarks(cat: BookmarkCategory) -> Result<(), Error> {
-        let bookmark_items: Vec<BookmarksItem> = {
-            let mut bookmark_api: MutexGuard<BookmarkApi> = Self::init();
-            bookmark_api.filter_marks_by_category(Some(&cat.id))
-        };
-        future::try_join_all(bookmark_items.into_iter().map(|item| Self::sync_bookmark(item, cat.clone())))
-            .await
-            .ok();
-        Ok(())
+    fn diff_sources(&mut self) -> Vec<(SourceInfo, String, BookmarkDiff)> {
+        let sync_state = sync::load_sync_state();
+        let files: Vec<(SourceInfo, String)> = self
+            .get_bookmark_files()
+            .iter()
+            .map(|(info, bookmark_file)| (info.clone(), bookmark_file.checksum.clone()))
+            .collect();
+        let categories = self.get_categories().clone();
+        let bookmarks = self.get_raw_bookmarks().clone();
+        let mut diffs = Vec::new();
+        for (info, checksum) in files.into_iter() {
+            let cached = self.db().marks_for_source(&info.id).unwrap_or_default();
+            // firefox and html sources have no checksum and are always diffed
+            let unchanged = !checksum.is_empty()
+                && !cached.is_empty()
+                && sync_state.checksums.get(&info.id) == Some(&checksum);
+            if unchanged {
+                continue;
+            }
+            let source_items: Vec<BookmarksItem> = bookmarks
+                .iter()
+                .filter(|item| item.source.as_ref() == Some(&info))
+                .cloned()
+                .collect();
+            let mut current = Vec::new();
+            BookmarksItem::collect_marks(&source_items, &BookmarkCategory::default(), &categories, &mut current);
+            diffs.push((info, checksum, sync::diff(current, cached)));
+        }
+        diffs
     }
-    async fn sync_bookmark(bookmark: BookmarksItem, cat: BookmarkCategory) -> Result<(), Error> {
-        println!("======================= started to sync bookmark");
-        let mut item_exists: bool = false;
+    /// false when some bookmark could not be scraped, with everything that went wrong
+    async fn apply_diff(info: &SourceInfo, diff: BookmarkDiff, reporter: &SyncReporter) -> (bool, Vec<Error>) {
+        if diff.is_empty() {
+            return (true, Vec::new());
+        }
+        info!(
+            "syncing source=\"{}\" added={} removed={} renamed={} moved={} url_changed={}",
+            info,
+            diff.added.len(),
+            diff.removed.len(),
+            diff.renamed.len(),
+            diff.moved.len(),
+            diff.url_changed.len()
+        );
+        let mut errors: Vec<Error> = Vec::new();
         {
-            let bookmark_api = Self::init();
-            let db = &bookmark_api.db;
-            let item = db.get::<MarkData>(&bookmark.id);
-            if item.is_ok() {
-                println!("-------------------------- item is ok");
-                item_exists = true;
-            } else {
-                println!("============================= item is not ok {:#?}", item.err());
+            let mut bookmark_api = Self::init();
+            let db = bookmark_api.db();
+            for (id, mark) in diff.removed.iter() {
+                Self::remove_cached_img(mark);
+                if let Some(copy) = &mark.archive {
+                    copy.remove();
+                }
+                if let Err(error) = db.delete(id) {
+                    errors.push(error.with_bookmark(id));
+                }
+            }
+            for (id, mark) in diff.updated.iter() {
+                if let Err(error) = db.save(id, mark) {
+                    errors.push(error.with_bookmark(id));
+                }
             }
         }
-        if !item_exists {
-            println!("======================= saved item is not exist");
-            Self::perform_scrape(&bookmark, cat).await;
+        let mut to_scrape = diff.added;
+        for (item, category, old_mark) in diff.url_changed.into_iter() {
+            Self::remove_cached_img(&old_mark);
+            // archived again from the new url
+            if let Some(copy) = &old_mark.archive {
+                copy.remove();
+            }
+            to_scrape.push((item, category));
         }
-        Ok(())
+        let scraped: Vec<(bool, Vec<Error>)> = futures::stream::iter(to_scrape.into_iter())
+            .map(|(item, cat)| async move { Self::perform_scrape(&item, cat, reporter).await })
+            .buffer_unordered(user_config::get().scrape_concurrency)
+            .collect()
+            .await;
+        let mut complete = true;
+        for (saved, scrape_errors) in scraped.into_iter() {
+            complete = complete && saved;
+            errors.extend(scrape_errors);
+        }
+        (complete, errors)
     }
-    async fn perform_scrape(bookmark: &BookmarksItem, cat: BookmarkCategory) {
-        let res = timeout(Duration::from_secs(30), Self::scrap_bookmark(&bookmark, &cat)).await;
-        if res.is_ok() {
-            if res.as_ref().unwrap().is_ok() {
-                let mark_data = res.as_ref().unwrap().as_ref().unwrap();
-                let bookmark_api = Self::init();
-                let db = &bookmark_api.db;
-                db.save_with_id(mark_data, &bookmark.id).ok();
-            }
-        } else {
-            let res = timeout(Duration::from_secs(30), Self::scrap_bookmark(&bookmark, &cat)).await;
-            if res.is_ok() {
-                if res.as_ref().unwrap().is_ok() {
-                    let mark_data = res.as_ref().unwrap().as_ref().unwrap();
-                    let bookmark_api = Self::init();
-                    let db = &bookmark_api.db;
-                    let save_res = db.save_with_id(mark_data, &bookmark.id);
-                    println!("========================= save result is {:?}", save_res);
+    fn remove_cached_img(mark: &MarkData) {
+        let img_name = mark.image.split('?').next().unwrap_or_default();
+        if !img_name.is_empty() && img_name != config::DEFAULT_IMG_PATH {
+            fs::remove_file(config::get_full_img_cache_path().join(img_name)).ok();
+        }
+    }
+    /// scrapes and caches a single bookmark, gives it a second try when the first one times out.
+    /// true when the mark was saved, the errors may also be about its image only
+    async fn perform_scrape(bookmark: &BookmarksItem, cat: BookmarkCategory, reporter: &SyncReporter) -> (bool, Vec<Error>) {
+        let link = bookmark.url.clone().unwrap_or_default();
+        reporter.started(&link);
+        let scrape_timeout = Duration::from_secs(user_config::get().scrape_timeout_secs);
+        let started = Instant::now();
+        let mut problems: Vec<Error> = Vec::new();
+        let mut res = timeout(scrape_timeout, Self::scrap_bookmark(bookmark, &cat, &mut problems)).await;
+        if res.is_err() {
+            info!("scrape timed out, trying again bookmark={} url={}", bookmark.id, link);
+            problems.clear();
+            res = timeout(scrape_timeout, Self::scrap_bookmark(bookmark, &cat, &mut problems)).await;
+        }
+        let saved = match res {
+            Ok(Ok(mark_data)) => {
+                let (saved, new) = {
+                    let mut bookmark_api = Self::init();
+                    let db = bookmark_api.db();
+                    // a changed url is scraped again under the same id
+                    let new = db.get(&bookmark.id).map_or(true, |old| old.is_none());
+                    (db.save(&bookmark.id, &mark_data), new)
+                };
+                if saved.is_ok() {
+                    reporter.mark_saved(&mark_data, new);
                 }
-            }
+                saved
+            },
+            Ok(Err(error)) => Err(error),
+            Err(_) => Err(Error::new(
+                ErrorKind::Timeout,
+                format!("the page took longer than {}s to scrape, twice", scrape_timeout.as_secs()),
+            )),
+        };
+        let duration_ms = started.elapsed().as_millis();
+        reporter.finished(saved.is_ok());
+        if let Err(error) = saved {
+            warn!(
+                "scrape failed bookmark={} url={} duration_ms={} outcome=failed error=\"{}\"",
+                bookmark.id,
+                link,
+                duration_ms,
+                error
+            );
+            problems.push(error);
+            let problems = problems.into_iter().map(|error| error.with_bookmark(&bookmark.id).with_url(&link)).collect();
+            return (false, problems);
         }
+        info!(
+            "scraped bookmark={} url={} duration_ms={} outcome=saved image_problems={}",
+            bookmark.id,
+            link,
+            duration_ms,
+            problems.len()
+        );
+        (true, problems.into_iter().map(|error| error.with_bookmark(&bookmark.id)).collect())
     }
     fn cache_bookmark_img(img_data: &Vec<u8>, img_path: &str) -> Result<String, Error> {
-        let dir_path = Path::new(dirs::cache_dir().unwrap().as_path()).join(config::CACHE_IMG_PATH);
-        let file = OpenOptions::new()
+        let dir_path = config::get_full_img_cache_path();
+        let mut file = OpenOptions::new()
             .create(true)
             .write(true)
-            .open(&dir_path.join(img_path));
-        if file.is_ok() {
-            let cache_result = file.unwrap().write_all(img_data);
-            if cache_result.is_ok() {
-                Ok(img_path.to_string())
-            } else {
-                Ok(config::DEFAULT_IMG_PATH.to_string())
-            }
-        } else {
-            Ok(config::DEFAULT_IMG_PATH.to_string())
+            .truncate(true)
+            .open(&dir_path.join(img_path))?;
+        file.write_all(img_data)?;
+        Ok(img_path.to_string())
+    }
+    /// imported bookmarks may carry a base64 data uri favicon, it's used when the page has no preview image
+    fn cache_icon(item: &BookmarksItem) -> String {
+        let icon_data = item
+            .icon
+            .as_ref()
+            .and_then(|icon| icon.strip_prefix("data:image/"))
+            .and_then(|icon| icon.split_once(";base64,"))
+            .and_then(|(ext, data)| base64::decode(data).ok().map(|bytes| (ext.to_string(), bytes)));
+        match icon_data {
+            Some((ext, bytes)) if bytes.len() > 0 => {
+                let img_name = format!("{}.{}", item.id, ext.replace("x-icon", "ico").replace("svg+xml", "svg"));
+                Self::cache_bookmark_img(&bytes, &img_name).unwrap_or(config::DEFAULT_IMG_PATH.to_string())
+            },
+            _ => config::DEFAULT_IMG_PATH.to_string()
         }
     }
-    fn get_extension_from_filename(filename: &str) -> Option<&str> {
-        Path::new(filename).extension().and_then(OsStr::to_str)
+    /// the visible text of the page for the search index, scripts and styles left out
+    fn extract_page_text(html: &Html) -> String {
+        let mut words: Vec<&str> = Vec::new();
+        for node in html.root_element().descendants() {
+            if let Some(text) = node.value().as_text() {
+                let hidden = node
+                    .parent()
+                    .and_then(|parent| parent.value().as_element())
+                    .map_or(false, |element| matches!(element.name(), "script" | "style" | "noscript" | "template" | "head" | "title"));
+                if !hidden {
+                    words.extend(text.split_whitespace());
+                }
+            }
+            if words.len() >= MAX_PAGE_TEXT_WORDS {
+                break;
+            }
+        }
+        words.truncate(MAX_PAGE_TEXT_WORDS);
+        words.join(" ")
     }
-    async fn scrap_bookmark(item: &BookmarksItem, cat: &BookmarkCategory) -> Result<MarkData, Error> {
+    /// a page that can't be loaded still gives a mark, a dead one. the image problems are
+    /// put in problems and the default image is used
+    async fn scrap_bookmark(item: &BookmarksItem, cat: &BookmarkCategory, problems: &mut Vec<Error>) -> Result<MarkData, Error> {
         let link = item.url.clone();
-        let img_url: String = if link.is_some() {
+        let mut page_text = String::new();
+        let mut meta = PageMeta::default();
+        let mut link_status = LinkStatus::Dead;
+        let img_url: String = if let Some(link) = link {
             let img_url = {
-                let link_result = link_preview::fetch::fetch(link.unwrap().as_str()).await;
+                let link_result = link_preview::fetch::fetch(link.as_str()).await;
+                if let Err(error) = &link_result {
+                    warn!(
+                        "page couldn't be loaded, the mark is kept as dead bookmark={} url={} error=\"{:?}\"",
+                        item.id,
+                        link,
+                        error
+                    );
+                }
                 if link_result.is_ok() {
+                    let html = link_result.unwrap();
+                    link_status = LinkStatus::Alive;
+                    // the article alone for the reader, the whole page when it isn't one
+                    page_text = readability::extract_article(&html).unwrap_or_else(|| Self::extract_page_text(&html));
+                    meta = page_meta::extract(&html);
                     let img_url =
-                        link_preview::LinkPreview::find_first_image_url(&link_result.unwrap());
+                        link_preview::LinkPreview::find_first_image_url(&html);
                     img_url
                 } else {
                     None
                 }
             };
-            if img_url.is_some() {
-                let img_data = Self::fetch_image(img_url.as_ref().unwrap().as_str()).await;
-                let mut img_name = String::from(&item.id);
-                img_name.push('.');
-                img_name.push_str(
-                    Self::get_extension_from_filename(img_url.as_ref().unwrap().as_str())
-                        .unwrap_or("png"),
-                );
-                let min_length: usize = 0;
-                if img_data.is_ok() && img_data.as_ref().unwrap().len() > min_length {
-                    let saved_path =
-                        Self::cache_bookmark_img(img_data.as_ref().unwrap(), &img_name).unwrap();
-                    saved_path
-                } else {
+            if let Some(img_url) = img_url {
+                let cached = match Self::fetch_image(img_url.as_str()).await {
+                    Ok(img_data) if img_data.is_empty() => Ok(config::DEFAULT_IMG_PATH.to_string()),
+                    // the extension of the url can lie, the bytes can't
+                    Ok(img_data) => match utils::image_extension(&img_data) {
+                        Some(extension) => Self::cache_bookmark_img(&img_data, &format!("{}.{}", item.id, extension)),
+                        None => Err(Error::new(ErrorKind::ImageDecode, "the preview image isn't in a format that can be shown")),
+                    },
+                    Err(error) => Err(error),
+                };
+                cached.unwrap_or_else(|error| {
+                    warn!(
+                        "preview image couldn't be cached, the default one is used bookmark={} url={} image={} error=\"{}\"",
+                        item.id,
+                        link,
+                        img_url,
+                        error
+                    );
+                    problems.push(error.with_url(img_url.as_str()));
                     config::DEFAULT_IMG_PATH.to_string()
-                }
+                })
             } else {
-                config::DEFAULT_IMG_PATH.to_string()
+                if matches!(link_status, LinkStatus::Alive) {
+                    info!("page has no preview image, the favicon or default one is used bookmark={} url={}", item.id, link);
+                }
+                Self::cache_icon(item)
             }
         } else {
             config::DEFAULT_IMG_PATH.to_string()
         };
+        // the name given to the bookmark wins over the one of the page
+        let title = if item.name.trim().is_empty() {
+            meta.title.clone().unwrap_or_default()
+        } else {
+            item.name.clone()
+        };
         Ok(MarkData::new(MarkData {
-            title: item.name.clone(),
-            description: format!(""),
+            title,
+            description: meta.description.clone().unwrap_or_default(),
             image: img_url,
-            content: item.id.clone(),
+            content: page_text,
             image_data: None,
             category: cat.to_owned(),
-            link: item.url.as_ref().unwrap().clone()
+            link: item.url.clone().unwrap_or_default(),
+            source: item.source.clone(),
+            date_added: utils::chrome_time_to_unix(&item.date_added),
+            date_last_used: item.date_last_used.as_ref().and_then(|date| utils::chrome_time_to_unix(date)),
+            link_status,
+            tags: item.tags.clone(),
+            snippet: None,
+            id: Some(item.id.clone()),
+            score: None,
+            meta,
+            archive: None,
+            health: None,
         }))
     }
     pub async fn fetch_image(image_url: &str) -> Result<Vec<u8>, Error> {
         let url = format!("{}", image_url);
         let bytes = {
             let client = reqwest::Client::builder()
-                .timeout(Duration::from_secs(30))
-                .build()
-                .ok()
-                .unwrap();
-            let result = client.get(&url).send().await;
-            if result.is_ok() {
-                result.unwrap().bytes().await?.as_ref().to_vec()
-            } else {
-                Vec::new()
-            }
+                .timeout(Duration::from_secs(user_config::get().scrape_timeout_secs))
+                .build()?;
+            client.get(&url).send().await?.error_for_status()?.bytes().await?.to_vec()
         };
         Ok(bytes)
     }
     pub async fn load_marks(cat: BookmarkCategory) -> Result<Vec<MarkData>, Error> {
-        let mut items: Vec<MarkData> = Vec::new();
-        let bookmark_api = Self::init();
-        let db: &Store = &bookmark_api.db;
-        let result = db.all::<MarkData>();
-        if result.is_ok() {
-            let all_data = result.unwrap();
-            items = all_data
-                .into_iter()
-                .filter(|(_, item)| {
-                    cat.id.as_str().eq(item.category.id.as_str())
-                })
-                .map(|(_, item)| item)
-                .collect();
+        let mut bookmark_api = Self::init();
+        let page = QueryBuilder::new()
+            .filter_by_categories(Some(vec![cat.id]))
+            .result(bookmark_api.db())?;
+        Ok(page.marks)
+    }
+    pub fn load_marks_sync(query: MarksQuery) -> Result<MarksPage, Error> {
+        let mut bookmark_api = Self::init();
+        let category_ids = match &query.category {
+            Some(cat) if query.include_subfolders => Some(bookmark_api.get_category_tree_ids(&cat.id)),
+            Some(cat) => Some(vec![cat.id.clone()]),
+            None => None,
+        };
+        let visible_source_ids = bookmark_api.visible_source_ids();
+        let (text_search, regex_search) = if query.regex {
+            (None, query.search)
         } else {
+            (query.search, None)
+        };
+        QueryBuilder::new()
+        .filter_by_sources(visible_source_ids)
+        .filter_by_source(query.source_id)
+        .filter_by_categories(category_ids)
+        .filter_by_date(query.added_from, query.added_to)
+        .search(text_search)
+        .search_regex(regex_search)
+        .sort(query.sort)
+        .paginate(query.after, query.page_size.unwrap_or(user_config::get().page_size))
+        .result(bookmark_api.db())
+    }
+    /// the marks of every profile that is part of the current library
+    fn visible_marks(&mut self) -> Result<Vec<MarkData>, Error> {
+        let visible_source_ids = self.visible_source_ids();
+        let page = QueryBuilder::new()
+            .filter_by_sources(visible_source_ids)
+            .result(self.db())?;
+        Ok(page.marks)
+    }
+    /// copies the picked bookmarks.html into the imports dir and turns it on as a source
+    pub async fn import_html() -> Result<(), Error> {
+        let file = rfd::AsyncFileDialog::new()
+            .add_filter("bookmarks", &["html", "htm"])
+            .pick_file()
+            .await;
+        if let Some(file) = file {
+            let imports_dir = config::get_imports_dir();
+            fs::create_dir_all(&imports_dir)?;
+            let import_path = imports_dir.join(file.file_name());
+            fs::copy(file.path(), &import_path)?;
+            let info = NetscapeSource::new(import_path).info();
+            let mut bookmark_api = Self::init();
+            bookmark_api.sources = source::discover_sources();
+            bookmark_api.set_source_enabled(&info.id, true);
         }
-        Ok(items)
+        Ok(())
     }
-    pub fn load_marks_sync(last_index: usize, category: Option<BookmarkCategory>, search: Option<String>) -> Result<(bool, Vec<MarkData>), Error> {
-        let bookmark_api = Self::init();
-        let db: &Store = &bookmark_api.db;
-        let result = db.all::<MarkData>();
-        let (has_more, items): (bool, Vec<MarkData>) = QueryBuilder::new(result)
-        .filter_by_category(category)
-        .search(search)
-        .paginate(last_index)
-        .result();
-        Ok((has_more, items))
+    pub async fn export_html() -> Result<(), Error> {
+        let file = rfd::AsyncFileDialog::new()
+            .add_filter("bookmarks", &["html"])
+            .set_file_name("bookmarks.html")
+            .save_file()
+            .await;
+        if let Some(file) = file {
+            let (marks, categories) = {
+                let mut bookmark_api = Self::init();
+                (bookmark_api.visible_marks()?, bookmark_api.get_categories().clone())
+            };
+            fs::write(file.path(), netscape::export(&marks, &categories))?;
+        }
+        Ok(())
     }
     pub async fn perform_load() -> Result<(), Error> {
         Ok(())
@@ -508,8 +1066,9 @@ impl BookmarkApi {
         {
             Self::reset_bookmarks();
             let mut bookmark_api = Self::init();
-            fs::remove_dir_all(Path::new(dirs::cache_dir().unwrap().as_path()).join(config::CACHE_MAIN_DIR)).ok();
-            bookmark_api.db = Self::bootstrap();
+            // the connection has to be closed before its file can go
+            bookmark_api.db = None;
+            fs::remove_dir_all(config::get_cache_main_dir()).ok();
         }
         Ok(())
     }
@@ -524,6 +1083,38 @@ pub struct MarkData {
     pub image_data: Option<Vec<u8>>,
     pub category: BookmarkCategory,
     pub link: String,
+    #[serde(default)]
+    pub source: Option<SourceInfo>,
+    #[serde(default)]
+    pub link_status: LinkStatus,
+    /// lowercased, from the browser (firefox, bookmarks.html) tags
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// the page's own title, canonical url, author and so on
+    #[serde(default)]
+    pub meta: PageMeta,
+    /// the self-contained copy of the page, for marks in archived folders
+    #[serde(default)]
+    pub archive: Option<ArchivedCopy>,
+    /// how the link answered the last time it was checked
+    #[serde(default)]
+    pub health: Option<LinkHealth>,
+    /// the matching part of the page with the search terms wrapped in SNIPPET_START / SNIPPET_END,
+    /// only set on search results
+    #[serde(skip)]
+    pub snippet: Option<String>,
+    /// the key the mark is cached under, only set on marks loaded from the cache or just synced
+    #[serde(skip)]
+    pub id: Option<String>,
+    /// how well a search result matches, higher is better, only set on search results
+    #[serde(skip)]
+    pub score: Option<f64>,
+    /// unix seconds
+    #[serde(default)]
+    pub date_added: Option<i64>,
+    /// unix seconds, None when the bookmark was never opened
+    #[serde(default)]
+    pub date_last_used: Option<i64>,
 }
 
 impl MarkData {
@@ -532,85 +1123,544 @@ impl MarkData {
     }
 }
 
+/// whether the page answered the last time it was scraped
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkStatus {
+    Unknown,
+    Alive,
+    Dead,
+}
+
+impl Default for LinkStatus {
+    fn default() -> Self {
+        LinkStatus::Unknown
+    }
+}
+
+impl fmt::Display for LinkStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            LinkStatus::Unknown => "unknown",
+            LinkStatus::Alive => "alive",
+            LinkStatus::Dead => "dead",
+        })
+    }
+}
+
+impl FromStr for LinkStatus {
+    type Err = ();
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        match val.to_lowercase().as_str() {
+            "unknown" => Ok(LinkStatus::Unknown),
+            "alive" => Ok(LinkStatus::Alive),
+            "dead" => Ok(LinkStatus::Dead),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SortOrder {
+    Unsorted,
+    Newest,
+    Oldest,
+    RecentlyUsed,
+    NeverUsed,
+}
+
+impl SortOrder {
+    pub const ALL: [SortOrder; 5] = [
+        SortOrder::Unsorted,
+        SortOrder::Newest,
+        SortOrder::Oldest,
+        SortOrder::RecentlyUsed,
+        SortOrder::NeverUsed,
+    ];
+}
+
+impl Default for SortOrder {
+    fn default() -> Self {
+        SortOrder::Unsorted
+    }
+}
+
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match self {
+            SortOrder::Unsorted => "unsorted",
+            SortOrder::Newest => "newest",
+            SortOrder::Oldest => "oldest",
+            SortOrder::RecentlyUsed => "recently used",
+            SortOrder::NeverUsed => "never used",
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct MarksQuery {
+    pub category: Option<BookmarkCategory>,
+    pub include_subfolders: bool,
+    pub search: Option<String>,
+    /// the search is a regex over title, description and link instead of the query syntax
+    pub regex: bool,
+    pub source_id: Option<String>,
+    pub sort: SortOrder,
+    /// unix seconds, both ends included
+    pub added_from: Option<i64>,
+    pub added_to: Option<i64>,
+    /// marks per page, the page_size of the user config when not set
+    pub page_size: Option<usize>,
+    /// the page starts after the mark this cursor points to, the first page when None
+    pub after: Option<PageCursor>,
+}
+
+/// one value of a sort key, the sql values a cursor is compared with
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum CursorKey {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+}
+
+impl From<Value> for CursorKey {
+    fn from(val: Value) -> Self {
+        match val {
+            Value::Integer(val) => CursorKey::Integer(val),
+            Value::Real(val) => CursorKey::Real(val),
+            Value::Text(val) => CursorKey::Text(val),
+            Value::Null | Value::Blob(_) => CursorKey::Null,
+        }
+    }
+}
+
+impl From<CursorKey> for Value {
+    fn from(key: CursorKey) -> Self {
+        match key {
+            CursorKey::Null => Value::Null,
+            CursorKey::Integer(val) => Value::Integer(val),
+            CursorKey::Real(val) => Value::Real(val),
+            CursorKey::Text(val) => Value::Text(val),
+        }
+    }
+}
+
+impl CursorKey {
+    /// the order sqlite gives the values, nulls first and numbers before text
+    fn sql_cmp(&self, other: &CursorKey) -> Ordering {
+        let rank = |key: &CursorKey| match key {
+            CursorKey::Null => 0,
+            CursorKey::Integer(_) | CursorKey::Real(_) => 1,
+            CursorKey::Text(_) => 2,
+        };
+        let number = |key: &CursorKey| match key {
+            CursorKey::Integer(val) => *val as f64,
+            CursorKey::Real(val) => *val,
+            _ => 0.0,
+        };
+        match (self, other) {
+            (CursorKey::Integer(a), CursorKey::Integer(b)) => a.cmp(b),
+            (CursorKey::Text(a), CursorKey::Text(b)) => a.cmp(b),
+            (a, b) if rank(a) == 1 && rank(b) == 1 => number(a).partial_cmp(&number(b)).unwrap_or(Ordering::Equal),
+            (a, b) => rank(a).cmp(&rank(b)),
+        }
+    }
+}
+
+/// where the next page starts: the sort keys of the last mark shown rather than a row offset,
+/// so marks synced or removed in between don't shift the pages
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct PageCursor {
+    keys: Vec<CursorKey>,
+    /// the time the search scores were worked out at
+    now: i64,
+}
+
+impl PageCursor {
+    /// whether the mark comes after the cursor in the sort, i.e. on a page that isn't loaded yet.
+    /// the same keys as QueryBuilder::sort_keys, searches are ranked and can't be told
+    pub fn precedes(&self, sort: SortOrder, id: &str, mark: &MarkData) -> bool {
+        let or_max = |val: Option<i64>| CursorKey::Integer(val.unwrap_or(i64::MAX));
+        let mut keys = match sort {
+            SortOrder::Unsorted => Vec::new(),
+            SortOrder::Newest => vec![or_max(mark.date_added.map(|added| -added))],
+            SortOrder::Oldest => vec![or_max(mark.date_added)],
+            SortOrder::RecentlyUsed => vec![or_max(mark.date_last_used.map(|used| -used))],
+            SortOrder::NeverUsed => vec![CursorKey::Integer(mark.date_last_used.unwrap_or(-1)), or_max(mark.date_added)],
+        };
+        keys.push(CursorKey::Text(id.to_string()));
+        if keys.len() != self.keys.len() {
+            return true;
+        }
+        keys.iter()
+            .zip(self.keys.iter())
+            .map(|(key, cursor_key)| key.sql_cmp(cursor_key))
+            .find(|order| *order != Ordering::Equal)
+            .map_or(false, |order| order == Ordering::Greater)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MarksPage {
+    pub marks: Vec<MarkData>,
+    /// how many marks the query matches on all pages
+    pub total: usize,
+    pub next: Option<PageCursor>,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct MarkMeta{
     pub mark: Option<MarkData>,
     pub index: usize,
+    /// how many marks the query matches on all pages
     pub all_len: usize,
-    pub has_more: bool
+    pub has_more: bool,
+    pub next: Option<PageCursor>,
+    pub query: MarksQuery,
+    /// why the page couldn't be loaded, sent with the empty page
+    #[serde(skip)]
+    pub error: Option<Error>
 }
 
 pub trait IQueryBuilder{
-    fn new(items: Result<BTreeMap<String, MarkData>, std::io::Error>) -> Self;
-    fn paginate(&mut self, last_index: usize) -> &mut Self;
-    fn filter_by_category(&mut self, cat: Option<BookmarkCategory>) -> &mut Self;
+    fn new() -> Self;
+    fn paginate(&mut self, after: Option<PageCursor>, page_size: usize) -> &mut Self;
+    fn filter_by_categories(&mut self, cat_ids: Option<Vec<String>>) -> &mut Self;
+    fn filter_by_sources(&mut self, source_ids: Vec<String>) -> &mut Self;
+    fn filter_by_source(&mut self, source_id: Option<String>) -> &mut Self;
     fn search(&mut self, txt: Option<String>) -> &mut Self;
-    // fn append_cat_to_list(&mut self) -> &mut Self;
-    fn result (&self) -> (bool, Vec<MarkData>);
+    fn search_regex(&mut self, pattern: Option<String>) -> &mut Self;
+    fn filter_by_date(&mut self, from: Option<i64>, to: Option<i64>) -> &mut Self;
+    fn sort(&mut self, order: SortOrder) -> &mut Self;
+    fn result(&self, db: &MarkStore) -> Result<MarksPage, Error>;
 }
 
+/// builds the sql for a page of marks, every filter becomes a where condition on the indexed columns
 pub struct QueryBuilder{
-    items: Vec<MarkData>,
-    has_more: bool,
+    conditions: Vec<String>,
+    params: Vec<Value>,
+    sort: SortOrder,
+    // kept until result, the typo alternatives come from the index
+    search: Option<query::SearchQuery>,
+    regex: Option<Regex>,
+    // the cursor the page starts after and the page size
+    page: Option<(Option<PageCursor>, usize)>,
 }
-impl IQueryBuilder for QueryBuilder {
-    fn new(items: Result<BTreeMap<String, MarkData>, std::io::Error>) -> Self{
 
+impl QueryBuilder {
+    fn placeholders(count: usize) -> String {
+        vec!["?"; count].join(", ")
+    }
+    /// the columns the marks are ordered by, ascending and never null so a cursor can be compared
+    /// with them as a row value. the id comes last and makes the order total
+    fn sort_keys(&self, ranked: bool) -> Vec<String> {
+        let mut keys: Vec<String> = match self.sort {
+            // an explicit sort wins over the relevance of a search
+            SortOrder::Unsorted if ranked => vec![String::from("-score")],
+            SortOrder::Unsorted => Vec::new(),
+            SortOrder::Newest => vec![format!("COALESCE(-date_added, {})", i64::MAX)],
+            SortOrder::Oldest => vec![format!("COALESCE(date_added, {})", i64::MAX)],
+            SortOrder::RecentlyUsed => vec![format!("COALESCE(-date_last_used, {})", i64::MAX)],
+            // never opened bookmarks first, the oldest of them on top
+            SortOrder::NeverUsed => vec![
+                String::from("COALESCE(date_last_used, -1)"),
+                format!("COALESCE(date_added, {})", i64::MAX),
+            ],
+        };
+        keys.push(String::from("id"));
+        keys
+    }
+}
+
+impl IQueryBuilder for QueryBuilder {
+    fn new() -> Self{
         Self {
-            items: items.unwrap_or(BTreeMap::<String, MarkData>::new()).into_iter().map(|i| i.1).collect(),
-            has_more: false
+            conditions: Vec::new(),
+            params: Vec::new(),
+            sort: SortOrder::default(),
+            search: None,
+            regex: None,
+            page: None,
         }
     }
 
-    fn paginate(&mut self, last_index: usize) -> &mut Self{
-        let offset: usize = 4;
-        let next_slice = offset + last_index;
-            if self.items.len() >= last_index {
-                if self.items.len() >= next_slice {
-                    if self.items.len() >= next_slice + offset {
-                        self.has_more = true;
-                    } else {
-                        self.has_more = false;
-                    }
-                    self.items = self.items.as_slice()[last_index..next_slice].to_vec();
-                } else {
-                    self.has_more = false;
-                }
-            } else {
-                self.items = Vec::new();
-                self.has_more = false;
-            }
+    fn paginate(&mut self, after: Option<PageCursor>, page_size: usize) -> &mut Self{
+        self.page = Some((after, page_size.max(1)));
+        self
+    }
+
+    fn filter_by_categories(&mut self, cat_ids: Option<Vec<String>>) -> &mut Self {
+        match cat_ids {
+            Some(cat_ids) => {
+                self.conditions.push(format!("marks.category_id IN ({})", Self::placeholders(cat_ids.len())));
+                self.params.extend(cat_ids.into_iter().map(Value::Text));
+            },
+            None => ()
+        };
         self
     }
 
-    fn filter_by_category(&mut self, cat: Option<BookmarkCategory>) -> &mut Self {
-        match cat {
-            Some(cat) => self.items.retain(|item| {
-                cat.id.as_str().eq(item.category.id.as_str())
-            }),
+    fn filter_by_sources(&mut self, source_ids: Vec<String>) -> &mut Self {
+        self.conditions.push(format!("marks.source_id IN ({})", Self::placeholders(source_ids.len())));
+        self.params.extend(source_ids.into_iter().map(Value::Text));
+        self
+    }
+
+    fn filter_by_source(&mut self, source_id: Option<String>) -> &mut Self {
+        match source_id {
+            Some(source_id) => {
+                self.conditions.push(String::from("marks.source_id = ?"));
+                self.params.push(Value::Text(source_id));
+            },
             None => ()
         };
         self
     }
 
+    fn filter_by_date(&mut self, from: Option<i64>, to: Option<i64>) -> &mut Self {
+        if let Some(from) = from {
+            self.conditions.push(String::from("marks.date_added >= ?"));
+            self.params.push(Value::Integer(from));
+        }
+        if let Some(to) = to {
+            self.conditions.push(String::from("marks.date_added <= ?"));
+            self.params.push(Value::Integer(to));
+        }
+        self
+    }
+
+    fn sort(&mut self, order: SortOrder) -> &mut Self {
+        self.sort = order;
+        self
+    }
+
     fn search(&mut self, txt: Option<String>) -> &mut Self {
-        match txt {
-            Some(val) => {
-                let re = RegexBuilder::new(format!("({})", &val).as_str())
-                .multi_line(true).case_insensitive(true).ignore_whitespace(true).build().unwrap();
-                self.items.retain(|item| re.is_match(&item.title) || re.is_match(&item.description));
+        match txt.as_deref().map(query::parse) {
+            Some(Ok(search_query)) => {
+                self.search = Some(search_query);
             },
+            // the search box checks the query before loading, a broken one finds nothing
+            Some(Err(_)) => self.conditions.push(String::from("0")),
             None => ()
         };
         self
     }
-    // fn append_cat_to_list(&mut self) -> &Self {
-    //     self.items.iter_mut().for_each(|item| {
 
-    //     })
-    // }
-    fn result(&self) -> (bool, Vec<MarkData>) {
-        (self.has_more, self.items.to_vec())
+    fn search_regex(&mut self, pattern: Option<String>) -> &mut Self {
+        match pattern.as_deref().map(query::parse_regex) {
+            Some(Ok(re)) => {
+                self.conditions.push(String::from(
+                    "regex_match(marks.title, marks.description, json_extract(marks.data, '$.link'))"
+                ));
+                self.regex = Some(re);
+            },
+            Some(Err(_)) => self.conditions.push(String::from("0")),
+            None => ()
+        };
+        self
+    }
+
+    fn result(&self, db: &MarkStore) -> Result<MarksPage, Error> {
+        if let Some(re) = &self.regex {
+            // sqlite has no regex, the compiled one is handed to it as a function
+            let matcher = AssertUnwindSafe(re.clone());
+            db.conn().create_scalar_function(
+                "regex_match",
+                3,
+                FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+                move |ctx| {
+                    let AssertUnwindSafe(re) = &matcher;
+                    for i in 0..ctx.len() {
+                        let val: Option<String> = ctx.get(i)?;
+                        if val.map_or(false, |val| re.is_match(&val)) {
+                            return Ok(true);
+                        }
+                    }
+                    Ok(false)
+                },
+            )?;
+        }
+        let search = self.search.as_ref().map(|search_query| {
+            search_query.with_alternatives(|word, max_typos| db.similar_terms(word, max_typos).unwrap_or_default())
+        });
+        let mut conditions = self.conditions.clone();
+        let mut filter_params = self.params.clone();
+        if let Some(search_query) = &search {
+            conditions.extend(search_query.to_sql(&mut filter_params));
+        }
+        let ranking = search.as_ref().and_then(|search_query| search_query.ranking_query());
+        // the scores of every page of a search are worked out at the time of its first page
+        let now = match &self.page {
+            Some((Some(cursor), _)) => cursor.now,
+            _ => chrono::Utc::now().timestamp(),
+        };
+        let mut matches = String::from(
+            "SELECT marks.data AS data, marks.id AS id, marks.date_added AS date_added, marks.date_last_used AS date_last_used"
+        );
+        let mut params: Vec<Value> = Vec::new();
+        match &ranking {
+            // marks can match through a filter alone (site:x OR rust), so the text hits are left joined.
+            // bm25 weighs the title above the url, recently opened bookmarks get up to twice the score
+            Some(ranking) => {
+                matches.push_str(&format!(
+                    ", hits.snippet AS snippet,
+                    COALESCE(-hits.rank, 0.0)
+                        * (1.0 + COALESCE(1.0 / (1.0 + MAX(? - marks.date_last_used, 0) / ?), 0.0))
+                        AS score
+                    FROM marks LEFT JOIN (
+                        SELECT id, bm25(marks_fts, {}) AS rank, snippet(marks_fts, -1, '{}', '{}', '…', 16) AS snippet
+                        FROM marks_fts WHERE marks_fts MATCH ?
+                    ) AS hits ON hits.id = marks.id",
+                    mark_store::FTS_WEIGHTS, SNIPPET_START, SNIPPET_END
+                ));
+                params.push(Value::Integer(now));
+                params.push(Value::Real(RECENCY_BOOST_DAYS * 86400.0));
+                params.push(Value::Text(ranking.clone()));
+            },
+            None => matches.push_str(", NULL AS snippet, NULL AS score FROM marks"),
+        }
+        params.extend(filter_params.into_iter());
+        if !conditions.is_empty() {
+            matches.push_str(" WHERE ");
+            matches.push_str(&conditions.join(" AND "));
+        }
+        let keys = self.sort_keys(ranking.is_some());
+        let key_columns: Vec<String> = keys.iter().enumerate().map(|(i, key)| format!("{} AS key_{}", key, i)).collect();
+        let mut sql = format!("SELECT data, snippet, score, id, {} FROM ({}) AS matches", key_columns.join(", "), matches);
+        let mut page_params = params.clone();
+        if let Some((Some(cursor), _)) = &self.page {
+            // a cursor from another sort can't be compared, the list starts over
+            if cursor.keys.len() == keys.len() {
+                sql.push_str(&format!(" WHERE ({}) > ({})", keys.join(", "), Self::placeholders(keys.len())));
+                page_params.extend(cursor.keys.iter().cloned().map(Value::from));
+            }
+        }
+        sql.push_str(" ORDER BY ");
+        sql.push_str(&keys.join(", "));
+        if let Some((_, page_size)) = &self.page {
+            // one row more than a page tells if there is a next one
+            sql.push_str(" LIMIT ?");
+            page_params.push(Value::Integer(*page_size as i64 + 1));
+        }
+        let mut stmt = db.conn().prepare(&sql)?;
+        let rows = stmt
+            .query_map(params_from_iter(page_params.iter()), |row| {
+                let cursor_keys = (0..keys.len())
+                    .map(|i| row.get::<_, Value>(4 + i).map(CursorKey::from))
+                    .collect::<Result<Vec<CursorKey>, rusqlite::Error>>()?;
+                Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, Option<f64>>(2)?, row.get::<_, String>(3)?, cursor_keys))
+            })?
+            .collect::<Result<Vec<(String, Option<String>, Option<f64>, String, Vec<CursorKey>)>, rusqlite::Error>>()?;
+        let page_size = self.page.as_ref().map(|(_, page_size)| *page_size);
+        let has_more = page_size.map_or(false, |page_size| rows.len() > page_size);
+        let mut marks: Vec<MarkData> = Vec::new();
+        let mut last_keys: Vec<CursorKey> = Vec::new();
+        for (data, snippet, score, id, cursor_keys) in rows.into_iter() {
+            if Some(marks.len()) == page_size {
+                break;
+            }
+            let mut mark: MarkData = serde_json::from_str(&data)?;
+            mark.snippet = snippet;
+            mark.score = score;
+            mark.id = Some(id);
+            marks.push(mark);
+            last_keys = cursor_keys;
+        }
+        let next = if has_more {
+            Some(PageCursor { keys: last_keys, now })
+        } else {
+            None
+        };
+        let total = match page_size {
+            Some(_) => db.conn().query_row(
+                &format!("SELECT COUNT(*) FROM ({})", matches),
+                params_from_iter(params.iter()),
+                |row| row.get::<_, i64>(0),
+            )? as usize,
+            None => marks.len(),
+        };
+        Ok(MarksPage { marks, total, next })
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn mark(title: &str, link: &str) -> MarkData {
+        MarkData {
+            title: title.to_string(),
+            description: String::new(),
+            content: String::from("ownership and borrowing"),
+            image: config::DEFAULT_IMG_PATH.to_string(),
+            image_data: None,
+            category: BookmarkCategory::default(),
+            link: link.to_string(),
+            source: None,
+            link_status: LinkStatus::Unknown,
+            tags: Vec::new(),
+            meta: PageMeta::default(),
+            archive: None,
+            health: None,
+            snippet: None,
+            id: None,
+            score: None,
+            date_added: None,
+            date_last_used: None,
+        }
+    }
+
+    fn search(db: &MarkStore, txt: &str) -> Result<MarksPage, Error> {
+        QueryBuilder::new().search(Some(txt.to_string())).paginate(None, 12).result(db)
+    }
+
+    fn store() -> MarkStore {
+        let db = MarkStore::open_in_memory().unwrap();
+        db.save("1", &mark("The Rust Book", "https://doc.rust-lang.org/book/")).unwrap();
+        db.save("2", &mark("C++ reference", "https://en.cppreference.com/")).unwrap();
+        db
+    }
+
+    #[test]
+    fn searches_for_words_fts_would_choke_on() {
+        let db = store();
+        for txt in ["c++", "(", "\"", "++", "-", "OR", "\u{0}", "*", "\"\"", "-\"\"", "a OR", "NEAR(", "^", "site:", "rust*"] {
+            assert!(search(&db, txt).is_ok(), "{:?}", txt);
+        }
+        assert_eq!(search(&db, "c++").unwrap().total, 1);
+        assert_eq!(search(&db, "rust ++").unwrap().total, 1);
+    }
+
+    #[test]
+    fn searches_for_any_string() {
+        let db = store();
+        let mut pieces: Vec<String> = "aBz09 \"'-:*+()^.,/\\%_{}~\u{0}éß中".chars().map(String::from).collect();
+        pieces.extend([" OR ", "AND", "NOT", "NEAR(", "site:", "in:", "added:>", "status:"].iter().map(|piece| piece.to_string()));
+        let mut rng = rand::thread_rng();
+        for _ in 0..500 {
+            let len = rng.gen_range(0..12);
+            let txt: String = (0..len).map(|_| pieces[rng.gen_range(0..pieces.len())].as_str()).collect();
+            assert!(search(&db, &txt).is_ok(), "{:?}", txt);
+        }
+    }
+
+    #[test]
+    fn tells_the_marks_of_the_next_pages() {
+        let db = MarkStore::open_in_memory().unwrap();
+        for (id, added) in [("1", 100), ("2", 300), ("3", 200), ("4", 400)] {
+            db.save(id, &MarkData { date_added: Some(added), ..mark(id, id) }).unwrap();
+        }
+        let page = QueryBuilder::new().sort(SortOrder::Newest).paginate(None, 2).result(&db).unwrap();
+        let loaded: Vec<Option<String>> = page.marks.iter().map(|mark| mark.id.clone()).collect();
+        assert_eq!(loaded, vec![Some(String::from("4")), Some(String::from("2"))]);
+        let next = page.next.unwrap();
+        let newer = MarkData { date_added: Some(350), ..mark("5", "5") };
+        let older = MarkData { date_added: Some(150), ..mark("6", "6") };
+        let undated = mark("7", "7");
+        assert!(!next.precedes(SortOrder::Newest, "5", &newer));
+        assert!(next.precedes(SortOrder::Newest, "6", &older));
+        assert!(next.precedes(SortOrder::Newest, "7", &undated));
+    }
+}
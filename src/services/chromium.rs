@@ -0,0 +1,180 @@
+use crate::config;
+use crate::services::bookmark_api::BookmarkFile;
+use crate::services::source::{BookmarkSource, SourceError, SourceInfo};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+// every Bookmarks file chromium writes has these three root folders
+const BOOKMARK_ROOTS: [&str; 3] = ["bookmark_bar", "other", "synced"];
+
+#[derive(Deserialize, Debug, Default)]
+struct LocalState {
+    #[serde(default)]
+    profile: LocalStateProfiles,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct LocalStateProfiles {
+    #[serde(default)]
+    info_cache: HashMap<String, LocalStateProfileInfo>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LocalStateProfileInfo {
+    name: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChromiumBrowser {
+    Chrome,
+    Chromium,
+    Brave,
+    Edge,
+    Vivaldi,
+}
+
+impl ChromiumBrowser {
+    pub fn all() -> Vec<ChromiumBrowser> {
+        vec![
+            ChromiumBrowser::Chrome,
+            ChromiumBrowser::Chromium,
+            ChromiumBrowser::Brave,
+            ChromiumBrowser::Edge,
+            ChromiumBrowser::Vivaldi,
+        ]
+    }
+    pub fn key(&self) -> &'static str {
+        match self {
+            ChromiumBrowser::Chrome => "chrome",
+            ChromiumBrowser::Chromium => "chromium",
+            ChromiumBrowser::Brave => "brave",
+            ChromiumBrowser::Edge => "edge",
+            ChromiumBrowser::Vivaldi => "vivaldi",
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChromiumBrowser::Chrome => "Google Chrome",
+            ChromiumBrowser::Chromium => "Chromium",
+            ChromiumBrowser::Brave => "Brave",
+            ChromiumBrowser::Edge => "Microsoft Edge",
+            ChromiumBrowser::Vivaldi => "Vivaldi",
+        }
+    }
+    fn linux_dir(&self) -> &'static str {
+        match self {
+            ChromiumBrowser::Chrome => "google-chrome",
+            ChromiumBrowser::Chromium => "chromium",
+            ChromiumBrowser::Brave => "BraveSoftware/Brave-Browser",
+            ChromiumBrowser::Edge => "microsoft-edge",
+            ChromiumBrowser::Vivaldi => "vivaldi",
+        }
+    }
+    fn windows_dir(&self) -> &'static str {
+        match self {
+            ChromiumBrowser::Chrome => "Google/Chrome/User Data",
+            ChromiumBrowser::Chromium => "Chromium/User Data",
+            ChromiumBrowser::Brave => "BraveSoftware/Brave-Browser/User Data",
+            ChromiumBrowser::Edge => "Microsoft/Edge/User Data",
+            ChromiumBrowser::Vivaldi => "Vivaldi/User Data",
+        }
+    }
+    /// the "User Data" dir that holds the profiles of this browser
+    pub fn user_data_dir(&self) -> Option<PathBuf> {
+        match std::env::consts::OS {
+            "windows" => dirs::data_local_dir().map(|dir| dir.join(self.windows_dir())),
+            "linux" => dirs::config_dir().map(|dir| dir.join(self.linux_dir())),
+            _ => None,
+        }
+    }
+}
+
+pub struct ChromiumSource {
+    browser: ChromiumBrowser,
+    profile_dir: String,
+    profile_name: String,
+    path: PathBuf,
+}
+
+impl ChromiumSource {
+    pub fn new(browser: ChromiumBrowser, profile_dir: &str, profile_name: &str, path: PathBuf) -> Self {
+        ChromiumSource {
+            browser,
+            profile_dir: profile_dir.to_string(),
+            profile_name: profile_name.to_string(),
+            path,
+        }
+    }
+    /// a Bookmarks file picked by hand, the file path stands in for the profile dir
+    pub fn from_file(path: PathBuf) -> Self {
+        let profile_dir = path.to_string_lossy().to_string();
+        let profile_name = path
+            .parent()
+            .and_then(|parent| parent.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| profile_dir.clone());
+        Self::new(ChromiumBrowser::Chromium, &profile_dir, &profile_name, path)
+    }
+    /// every profile listed in the browser "Local State" file that has a bookmarks file,
+    /// falls back to the Default profile when Local State can't be read
+    pub fn discover(browser: ChromiumBrowser) -> Vec<Self> {
+        let user_data_dir = match browser.user_data_dir() {
+            Some(dir) => dir,
+            None => return Vec::new(),
+        };
+        let local_state: LocalState = fs::read_to_string(user_data_dir.join(config::CHROMIUM_LOCAL_STATE_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        let mut profiles: Vec<(String, String)> = local_state
+            .profile
+            .info_cache
+            .into_iter()
+            .map(|(dir, info)| {
+                let name = info.name.unwrap_or_else(|| dir.clone());
+                (dir, name)
+            })
+            .collect();
+        if profiles.is_empty() {
+            profiles.push((config::CHROMIUM_DEFAULT_PROFILE.to_string(), config::CHROMIUM_DEFAULT_PROFILE.to_string()));
+        }
+        profiles.sort();
+        profiles
+            .into_iter()
+            .filter_map(|(dir, name)| {
+                let path = user_data_dir.join(&dir).join(config::CHROMIUM_BOOKMARKS_FILE);
+                if path.is_file() {
+                    Some(Self::new(browser, &dir, &name, path))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl BookmarkSource for ChromiumSource {
+    fn info(&self) -> SourceInfo {
+        SourceInfo::new(self.browser.key(), self.browser.name(), &self.profile_dir, &self.profile_name)
+    }
+    fn read_bookmark_file(&self) -> Result<BookmarkFile, SourceError> {
+        let file = fs::File::open(&self.path).map_err(|error| SourceError::from_io(&self.path, error))?;
+        let value: serde_json::Value = serde_json::from_reader(BufReader::new(file))
+            .map_err(|error| SourceError::Corrupt(error.to_string()))?;
+        for root in BOOKMARK_ROOTS.iter() {
+            if value.get("roots").and_then(|roots| roots.get(root)).is_none() {
+                return Err(SourceError::MissingRoot(root.to_string()));
+            }
+        }
+        serde_json::from_value(value).map_err(|error| SourceError::Corrupt(error.to_string()))
+    }
+    fn watch_paths(&self) -> Vec<PathBuf> {
+        vec![self.path.clone()]
+    }
+}
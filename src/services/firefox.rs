@@ -0,0 +1,228 @@
+use crate::config;
+use crate::services::bookmark_api::{BookmarkBar, BookmarkFile, BookmarkType, BookmarksItem, BookmarksRoot};
+use crate::services::source::{BookmarkSource, SourceError, SourceInfo};
+use crate::utils::Error;
+use rusqlite::{Connection, OpenFlags};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const TOOLBAR_ROOT_GUID: &str = "toolbar_____";
+const MENU_ROOT_GUID: &str = "menu________";
+const UNFILED_ROOT_GUID: &str = "unfiled_____";
+const MOBILE_ROOT_GUID: &str = "mobile______";
+// every tag is a folder under this root holding one bookmark per tagged place
+const TAGS_ROOT_GUID: &str = "tags________";
+
+const TYPE_BOOKMARK: i64 = 1;
+const TYPE_FOLDER: i64 = 2;
+
+// firefox stores PRTime (microseconds since 1970), chrome counts microseconds since 1601
+const WINDOWS_TO_UNIX_EPOCH_MICROS: i64 = 11_644_473_600_000_000;
+
+struct PlacesRow {
+    id: i64,
+    r#type: i64,
+    parent: i64,
+    title: Option<String>,
+    date_added: Option<i64>,
+    last_visit_date: Option<i64>,
+    guid: String,
+    url: Option<String>,
+    place_id: Option<i64>,
+}
+
+pub struct FirefoxPlaces {
+    path: PathBuf,
+}
+
+impl FirefoxPlaces {
+    pub fn new(path: PathBuf) -> Self {
+        FirefoxPlaces { path }
+    }
+    /// firefox keeps places.sqlite locked while running, so the db and its wal file are copied into the cache dir first
+    fn copy_places(&self) -> Result<PathBuf, Error> {
+        let copy_path = config::get_places_copy_path(&self.info().id);
+        if let Some(parent) = copy_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // the wal and its index from an earlier copy don't belong to the new one, sqlite would
+        // replay them over it
+        for suffix in ["-wal", "-shm"] {
+            fs::remove_file(Self::with_suffix(&copy_path, suffix)).ok();
+        }
+        fs::copy(&self.path, &copy_path)?;
+        for suffix in ["-wal", "-shm"] {
+            let path = Self::with_suffix(&self.path, suffix);
+            if path.is_file() {
+                fs::copy(&path, Self::with_suffix(&copy_path, suffix))?;
+            }
+        }
+        Ok(copy_path)
+    }
+    fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+        let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(suffix);
+        path.with_file_name(file_name)
+    }
+    fn read_rows(db_path: &Path) -> Result<Vec<PlacesRow>, Error> {
+        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let mut stmt = conn.prepare(
+            "SELECT b.id, b.type, b.parent, b.title, b.dateAdded, b.guid, p.url, p.last_visit_date, b.fk
+             FROM moz_bookmarks b LEFT JOIN moz_places p ON b.fk = p.id
+             ORDER BY b.parent, b.position",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(PlacesRow {
+                    id: row.get(0)?,
+                    r#type: row.get(1)?,
+                    parent: row.get(2)?,
+                    title: row.get(3)?,
+                    date_added: row.get(4)?,
+                    guid: row.get(5)?,
+                    url: row.get(6)?,
+                    last_visit_date: row.get(7)?,
+                    place_id: row.get(8)?,
+                })
+            })?
+            .collect::<Result<Vec<PlacesRow>, rusqlite::Error>>()?;
+        Ok(rows)
+    }
+    /// the tag names of every tagged place
+    fn tags_by_place(rows: &Vec<PlacesRow>) -> HashMap<i64, Vec<String>> {
+        let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+        let tags_root = match rows.iter().find(|row| row.guid == TAGS_ROOT_GUID) {
+            Some(root) => root.id,
+            None => return tags,
+        };
+        let tag_names: HashMap<i64, String> = rows
+            .iter()
+            .filter(|row| row.parent == tags_root && row.r#type == TYPE_FOLDER)
+            .map(|row| (row.id, row.title.clone().unwrap_or_default().to_lowercase()))
+            .collect();
+        for row in rows.iter() {
+            if let (Some(tag), Some(place_id)) = (tag_names.get(&row.parent), row.place_id) {
+                tags.entry(place_id).or_default().push(tag.clone());
+            }
+        }
+        tags
+    }
+    fn to_item(row: &PlacesRow, children_map: &HashMap<i64, Vec<&PlacesRow>>, tags: &HashMap<i64, Vec<String>>) -> Option<BookmarksItem> {
+        let date_added = row
+            .date_added
+            .map(|date| (date + WINDOWS_TO_UNIX_EPOCH_MICROS).to_string())
+            .unwrap_or_default();
+        let date_last_used = row
+            .last_visit_date
+            .map(|date| (date + WINDOWS_TO_UNIX_EPOCH_MICROS).to_string());
+        match row.r#type {
+            TYPE_FOLDER => Some(BookmarksItem {
+                date_added,
+                date_last_used: None,
+                guid: row.guid.clone(),
+                id: row.id.to_string(),
+                name: row.title.clone().unwrap_or_default(),
+                r#type: BookmarkType::folder,
+                url: None,
+                children: Some(Self::to_items(row.id, children_map, tags)),
+                icon: None,
+                tags: Vec::new(),
+                source: None,
+            }),
+            // place: urls are firefox smart queries, not real pages
+            TYPE_BOOKMARK if row.url.as_ref().map_or(false, |url| !url.starts_with("place:")) => Some(BookmarksItem {
+                date_added,
+                date_last_used,
+                guid: row.guid.clone(),
+                id: row.id.to_string(),
+                name: row.title.clone().unwrap_or_else(|| row.url.clone().unwrap_or_default()),
+                r#type: BookmarkType::url,
+                url: row.url.clone(),
+                children: None,
+                icon: None,
+                tags: row.place_id.and_then(|place_id| tags.get(&place_id).cloned()).unwrap_or_default(),
+                source: None,
+            }),
+            _ => None,
+        }
+    }
+    fn to_items(parent_id: i64, children_map: &HashMap<i64, Vec<&PlacesRow>>, tags: &HashMap<i64, Vec<String>>) -> Vec<BookmarksItem> {
+        children_map
+            .get(&parent_id)
+            .map(|children| {
+                children
+                    .iter()
+                    .filter_map(|child| Self::to_item(child, children_map, tags))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl BookmarkSource for FirefoxPlaces {
+    fn info(&self) -> SourceInfo {
+        let profile = self
+            .path
+            .parent()
+            .and_then(|dir| dir.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        SourceInfo::new("firefox", "Firefox", &profile, &profile)
+    }
+    /// reads moz_bookmarks/moz_places and maps the firefox roots on the chrome ones:
+    /// toolbar -> bookmark_bar, menu & unfiled -> folders under other, mobile -> synced
+    fn read_bookmark_file(&self) -> Result<BookmarkFile, SourceError> {
+        if !self.path.is_file() {
+            return Err(SourceError::Missing(self.path.clone()));
+        }
+        let db_path = self.copy_places()?;
+        let rows = Self::read_rows(&db_path).map_err(|_| SourceError::Corrupt(String::from("places.sqlite can't be queried")))?;
+        let mut children_map: HashMap<i64, Vec<&PlacesRow>> = HashMap::new();
+        rows.iter().for_each(|row| children_map.entry(row.parent).or_default().push(row));
+        let tags = Self::tags_by_place(&rows);
+        let root_folder = |guid: &str, name: &str| -> Option<BookmarksItem> {
+            rows.iter().find(|row| row.guid == guid).map(|root| BookmarksItem {
+                date_added: String::new(),
+                date_last_used: None,
+                guid: root.guid.clone(),
+                id: root.id.to_string(),
+                name: name.to_string(),
+                r#type: BookmarkType::folder,
+                url: None,
+                children: Some(Self::to_items(root.id, &children_map, &tags)),
+                icon: None,
+                tags: Vec::new(),
+                source: None,
+            })
+        };
+        let root_bar = |guid: &str, name: &str| -> BookmarkBar {
+            let root = root_folder(guid, name);
+            BookmarkBar {
+                id: root.as_ref().map(|root| root.id.clone()),
+                name: Some(name.to_string()),
+                children: Some(root.and_then(|root| root.children).unwrap_or_default()),
+            }
+        };
+        // menu and unfiled stay two separate top level folders
+        let other: Vec<BookmarksItem> = vec![
+            root_folder(MENU_ROOT_GUID, "Bookmarks Menu"),
+            root_folder(UNFILED_ROOT_GUID, "Other Bookmarks"),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        Ok(BookmarkFile {
+            checksum: String::new(),
+            roots: BookmarksRoot {
+                bookmark_bar: root_bar(TOOLBAR_ROOT_GUID, "Bookmarks Toolbar"),
+                other: BookmarkBar { id: None, name: None, children: Some(other) },
+                synced: root_bar(MOBILE_ROOT_GUID, "Mobile Bookmarks"),
+            },
+        })
+    }
+    /// firefox writes to the wal file first and only checkpoints it into places.sqlite later
+    fn watch_paths(&self) -> Vec<PathBuf> {
+        vec![self.path.clone(), Self::with_suffix(&self.path, "-wal")]
+    }
+}
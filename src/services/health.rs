@@ -0,0 +1,365 @@
+use crate::config;
+use crate::user_config;
+use crate::services::bookmark_api::MarkData;
+use crate::utils::{self, Error};
+use reqwest::{redirect, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const MAX_REDIRECTS: usize = 10;
+/// a page taking longer than this to answer is listed as slow
+pub const SLOW_MS: u64 = 5000;
+/// links are checked again once their last check is older than this
+pub const CHECK_INTERVAL_SECS: i64 = 24 * 60 * 60;
+
+/// why a link could not be loaded at all
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LinkError {
+    /// the domain doesn't resolve anymore
+    Dns,
+    Tls(String),
+    Timeout,
+    Connect(String),
+    TooManyRedirects,
+    Other(String),
+}
+
+impl fmt::Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::Dns => write!(f, "the domain doesn't resolve"),
+            LinkError::Tls(reason) => write!(f, "tls error: {}", reason),
+            LinkError::Timeout => write!(f, "timed out"),
+            LinkError::Connect(reason) => write!(f, "can't connect: {}", reason),
+            LinkError::TooManyRedirects => write!(f, "too many redirects"),
+            LinkError::Other(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+/// what came back the last time a bookmarked url was requested
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkHealth {
+    /// the http status of the last response, None when there was none
+    pub status: Option<u16>,
+    /// where the redirects ended up
+    pub final_url: Option<String>,
+    /// every redirect on the way was a 301 or 308
+    pub permanent_redirect: bool,
+    pub error: Option<LinkError>,
+    pub elapsed_ms: u64,
+    /// unix seconds
+    pub checked: i64,
+}
+
+impl LinkHealth {
+    pub fn is_alive(&self) -> bool {
+        self.error.is_none() && self.status.map_or(false, |status| status < 400)
+    }
+    pub fn is_not_found(&self) -> bool {
+        matches!(self.status, Some(404) | Some(410))
+    }
+    pub fn is_gone_domain(&self) -> bool {
+        self.error == Some(LinkError::Dns)
+    }
+    /// the url the bookmark should point to instead, for permanent redirects only
+    pub fn moved_to(&self, link: &str) -> Option<&String> {
+        self.final_url
+            .as_ref()
+            .filter(|final_url| self.permanent_redirect && self.is_alive() && final_url.as_str() != link)
+    }
+    pub fn is_slow(&self) -> bool {
+        self.error.is_none() && self.elapsed_ms > SLOW_MS
+    }
+    pub fn needs_check(health: &Option<LinkHealth>) -> bool {
+        health
+            .as_ref()
+            .map_or(true, |health| chrono::Utc::now().timestamp() - health.checked > CHECK_INTERVAL_SECS)
+    }
+}
+
+/// reqwest keeps the dns and tls details in the sources of the error
+fn classify(error: &reqwest::Error) -> LinkError {
+    if error.is_timeout() {
+        LinkError::Timeout
+    } else if error.is_redirect() {
+        LinkError::TooManyRedirects
+    } else {
+        classify_chain(utils::error_chain(error), error.is_connect())
+    }
+}
+
+/// the messages of the error chain are all that tell a dns or tls failure from another one
+fn classify_chain(chain: String, is_connect: bool) -> LinkError {
+    let lowercase = chain.to_lowercase();
+    if lowercase.contains("dns error") || lowercase.contains("failed to lookup address") || lowercase.contains("name or service not known") {
+        LinkError::Dns
+    } else if lowercase.contains("certificate") || lowercase.contains("tls") || lowercase.contains("ssl") || lowercase.contains("handshake") {
+        LinkError::Tls(chain)
+    } else if is_connect {
+        LinkError::Connect(chain)
+    } else {
+        LinkError::Other(chain)
+    }
+}
+
+/// requests the link and follows its redirects, noting whether all of them were permanent
+pub async fn check(link: &str) -> LinkHealth {
+    check_with_timeout(link, Duration::from_secs(user_config::get().link_check_timeout_secs)).await
+}
+
+async fn check_with_timeout(link: &str, timeout: Duration) -> LinkHealth {
+    let redirects: Arc<Mutex<Vec<StatusCode>>> = Arc::new(Mutex::new(Vec::new()));
+    let seen = redirects.clone();
+    let policy = redirect::Policy::custom(move |attempt| {
+        if let Ok(mut seen) = seen.lock() {
+            seen.push(attempt.status());
+        }
+        if attempt.previous().len() > MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else {
+            attempt.follow()
+        }
+    });
+    let started = Instant::now();
+    let checked = chrono::Utc::now().timestamp();
+    let client = match reqwest::Client::builder().timeout(timeout).redirect(policy).build() {
+        Ok(client) => client,
+        Err(error) => {
+            return LinkHealth {
+                status: None,
+                final_url: None,
+                permanent_redirect: false,
+                error: Some(LinkError::Other(error.to_string())),
+                elapsed_ms: 0,
+                checked,
+            }
+        }
+    };
+    let response = client.get(link).send().await;
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    let redirects = redirects.lock().map(|redirects| redirects.clone()).unwrap_or_default();
+    let permanent_redirect = !redirects.is_empty()
+        && redirects
+            .iter()
+            .all(|status| *status == StatusCode::MOVED_PERMANENTLY || *status == StatusCode::PERMANENT_REDIRECT);
+    match response {
+        Ok(response) => LinkHealth {
+            status: Some(response.status().as_u16()),
+            final_url: Some(response.url().to_string()),
+            permanent_redirect,
+            error: None,
+            elapsed_ms,
+            checked,
+        },
+        Err(error) => LinkHealth {
+            status: error.status().map(|status| status.as_u16()),
+            final_url: error.url().map(|url| url.to_string()),
+            permanent_redirect,
+            error: Some(classify(&error)),
+            elapsed_ms,
+            checked,
+        },
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HealthEntry {
+    /// the bookmark id
+    pub id: String,
+    pub title: String,
+    pub link: String,
+    pub detail: String,
+    /// where a permanently redirected link ends up
+    pub moved_to: Option<String>,
+}
+
+/// the marks whose links need a look, a mark is listed under the first section it fits
+#[derive(Debug, Clone, Default)]
+pub struct HealthReport {
+    pub not_found: Vec<HealthEntry>,
+    pub gone_domains: Vec<HealthEntry>,
+    pub redirected: Vec<HealthEntry>,
+    pub slow: Vec<HealthEntry>,
+    pub failing: Vec<HealthEntry>,
+    pub checked: usize,
+    pub unchecked: usize,
+}
+
+impl HealthReport {
+    pub fn new(marks: Vec<(String, MarkData)>) -> Self {
+        let mut report = HealthReport::default();
+        for (id, mark) in marks.into_iter() {
+            let health = match &mark.health {
+                Some(health) => health,
+                None => {
+                    report.unchecked += 1;
+                    continue;
+                }
+            };
+            report.checked += 1;
+            let mut entry = HealthEntry {
+                id: id.clone(),
+                title: mark.title.clone(),
+                link: mark.link.clone(),
+                detail: String::new(),
+                moved_to: None,
+            };
+            if health.is_not_found() {
+                entry.detail = format!("{} not found", health.status.unwrap_or_default());
+                report.not_found.push(entry);
+            } else if health.is_gone_domain() {
+                entry.detail = format!("{} doesn't resolve anymore", utils::domain_of(&mark.link));
+                report.gone_domains.push(entry);
+            } else if let Some(moved_to) = health.moved_to(&mark.link) {
+                entry.detail = format!("moved to {}", moved_to);
+                entry.moved_to = Some(moved_to.clone());
+                report.redirected.push(entry);
+            } else if !health.is_alive() {
+                entry.detail = match (&health.error, health.status) {
+                    (Some(error), _) => error.to_string(),
+                    (None, Some(status)) => format!("answers with {}", status),
+                    (None, None) => String::from("no answer"),
+                };
+                report.failing.push(entry);
+            } else if health.is_slow() {
+                entry.detail = format!("took {:.1}s", health.elapsed_ms as f64 / 1000.0);
+                report.slow.push(entry);
+            }
+        }
+        report
+    }
+    /// the marks the "remove dead bookmarks" action drops
+    pub fn dead(&self) -> Vec<&HealthEntry> {
+        self.not_found.iter().chain(self.gone_domains.iter()).collect()
+    }
+}
+
+/// fixes made from the link health view, laid over the bookmarks when they are read.
+/// the browser's own bookmarks are left as they are, the html export carries the fixes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LinkFixes {
+    /// bookmark id to the url it points to now
+    pub relinked: HashMap<String, String>,
+    /// bookmark ids dropped from the library
+    pub removed: HashSet<String>,
+}
+
+pub fn load_link_fixes() -> LinkFixes {
+    fs::read_to_string(config::get_link_fixes_file_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_link_fixes(fixes: &LinkFixes) -> Result<(), Error> {
+    let content = serde_json::to_string_pretty(fixes).unwrap_or_default();
+    fs::write(config::get_link_fixes_file_path(), content)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    const TEST_TIMEOUT: Duration = Duration::from_secs(1);
+
+    fn respond(mut stream: TcpStream) {
+        let mut request: Vec<u8> = Vec::new();
+        let mut buf = [0u8; 1024];
+        while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+            match stream.read(&mut buf) {
+                Ok(0) | Err(_) => return,
+                Ok(read) => request.extend_from_slice(&buf[..read]),
+            }
+        }
+        let request = String::from_utf8_lossy(&request);
+        let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+        let (status, headers) = match path.as_str() {
+            "/missing" => ("404 Not Found", String::new()),
+            "/old" => ("301 Moved Permanently", String::from("Location: /new\r\n")),
+            "/moved-for-now" => ("302 Found", String::from("Location: /new\r\n")),
+            "/slow" => {
+                thread::sleep(TEST_TIMEOUT * 3);
+                ("200 OK", String::new())
+            }
+            _ => ("200 OK", String::new()),
+        };
+        let response = format!("HTTP/1.1 {}\r\n{}Content-Length: 2\r\nConnection: close\r\n\r\nok", status, headers);
+        stream.write_all(response.as_bytes()).ok();
+    }
+
+    /// a local stand-in for the bookmarked sites, the base url it answers on
+    fn serve() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                thread::spawn(move || respond(stream));
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    #[tokio::test]
+    async fn reports_a_missing_page() {
+        let health = check_with_timeout(&format!("{}/missing", serve()), TEST_TIMEOUT).await;
+        assert_eq!(health.status, Some(404));
+        assert!(health.is_not_found());
+        assert!(!health.is_alive());
+    }
+
+    #[tokio::test]
+    async fn reports_where_a_permanent_redirect_goes() {
+        let base = serve();
+        let link = format!("{}/old", base);
+        let health = check_with_timeout(&link, TEST_TIMEOUT).await;
+        assert_eq!(health.status, Some(200));
+        assert!(health.permanent_redirect);
+        assert_eq!(health.moved_to(&link), Some(&format!("{}/new", base)));
+    }
+
+    #[tokio::test]
+    async fn keeps_a_temporary_redirect() {
+        let link = format!("{}/moved-for-now", serve());
+        let health = check_with_timeout(&link, TEST_TIMEOUT).await;
+        assert!(health.is_alive());
+        assert!(!health.permanent_redirect);
+        assert_eq!(health.moved_to(&link), None);
+    }
+
+    #[test]
+    fn classifies_a_lookup_failure_as_dns() {
+        // the chain hyper gives when the system resolver doesn't know the domain
+        let chain = "error sending request for url (http://gone.invalid/): error trying to connect: \
+                     dns error: failed to lookup address information: Name or service not known";
+        assert_eq!(classify_chain(chain.to_string(), true), LinkError::Dns);
+        let refused = "error sending request for url (http://127.0.0.1:1/): error trying to connect: \
+                       tcp connect error: Connection refused (os error 111)";
+        assert_eq!(classify_chain(refused.to_string(), true), LinkError::Connect(refused.to_string()));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a system resolver, run with --ignored"]
+    async fn reports_a_domain_that_doesnt_resolve() {
+        // .invalid never resolves
+        let health = check_with_timeout("http://robozapress-health-check.invalid/", TEST_TIMEOUT).await;
+        assert_eq!(health.error, Some(LinkError::Dns));
+        assert!(health.is_gone_domain());
+    }
+
+    #[tokio::test]
+    async fn reports_a_slow_page_as_timed_out() {
+        let health = check_with_timeout(&format!("{}/slow", serve()), TEST_TIMEOUT).await;
+        assert_eq!(health.error, Some(LinkError::Timeout));
+        assert!(!health.is_alive());
+        assert!(health.elapsed_ms < (TEST_TIMEOUT * 3).as_millis() as u64);
+    }
+}
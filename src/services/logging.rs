@@ -0,0 +1,140 @@
+use crate::config;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// the log file is rotated once it grows past this
+const MAX_LOG_BYTES: u64 = 2 * 1024 * 1024;
+// robozaPress.log.1 up to robozaPress.log.3 are kept
+const KEPT_LOG_FILES: usize = 3;
+// how many events the diagnostics panel can show
+const MAX_RECENT_EVENTS: usize = 1000;
+
+/// a log line as the diagnostics panel shows it
+#[derive(Debug, Clone)]
+pub struct LogEvent {
+    /// local time, HH:MM:SS.mmm
+    pub time: String,
+    pub level: Level,
+    /// the module the event comes from
+    pub target: String,
+    /// "what happened key=value ...", the keys name the bookmark, url, duration and outcome
+    pub message: String,
+}
+
+static RECENT_EVENTS: Lazy<Mutex<VecDeque<LogEvent>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+struct AppLogger {
+    level: LevelFilter,
+    verbose: bool,
+    path: PathBuf,
+    file: Mutex<Option<File>>,
+}
+
+impl AppLogger {
+    fn rotated_path(path: &Path, index: usize) -> PathBuf {
+        let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(format!(".{}", index));
+        path.with_file_name(file_name)
+    }
+    /// robozaPress.log becomes robozaPress.log.1, the oldest one is dropped
+    fn rotate(path: &Path) {
+        fs::remove_file(Self::rotated_path(path, KEPT_LOG_FILES)).ok();
+        for index in (1..KEPT_LOG_FILES).rev() {
+            fs::rename(Self::rotated_path(path, index), Self::rotated_path(path, index + 1)).ok();
+        }
+        fs::rename(path, Self::rotated_path(path, 1)).ok();
+    }
+    fn open(path: &Path) -> Option<File> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).ok()?;
+        }
+        OpenOptions::new().create(true).append(true).open(path).ok()
+    }
+    fn write_to_file(&self, line: &str) {
+        let mut file = match self.file.lock() {
+            Ok(file) => file,
+            Err(_) => return,
+        };
+        let too_big = file
+            .as_ref()
+            .and_then(|file| file.metadata().ok())
+            .map_or(false, |metadata| metadata.len() > MAX_LOG_BYTES);
+        if too_big {
+            *file = None;
+            Self::rotate(&self.path);
+            *file = Self::open(&self.path);
+        }
+        if let Some(file) = file.as_mut() {
+            writeln!(file, "{}", line).ok();
+        }
+    }
+}
+
+impl Log for AppLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level && metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+    }
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let now = chrono::Local::now();
+        let event = LogEvent {
+            time: now.format("%H:%M:%S%.3f").to_string(),
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+        let line = format!(
+            "{} {:<5} {} {}",
+            now.format("%Y-%m-%d %H:%M:%S%.3f"),
+            event.level,
+            event.target,
+            event.message
+        );
+        self.write_to_file(&line);
+        // the terminal only gets warnings unless --verbose is on
+        if self.verbose || event.level <= Level::Warn {
+            eprintln!("{}", line);
+        }
+        if let Ok(mut events) = RECENT_EVENTS.lock() {
+            events.push_front(event);
+            events.truncate(MAX_RECENT_EVENTS);
+        }
+    }
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            if let Some(file) = file.as_mut() {
+                file.flush().ok();
+            }
+        }
+    }
+}
+
+/// logs to the rotated file in the cache dir, debug events are only kept when verbose
+pub fn init(verbose: bool) {
+    let path = config::get_log_file_path();
+    let level = if verbose { LevelFilter::Debug } else { LevelFilter::Info };
+    let logger = AppLogger {
+        level,
+        verbose,
+        file: Mutex::new(AppLogger::open(&path)),
+        path,
+    };
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+/// the newest events first
+pub fn recent_events() -> Vec<LogEvent> {
+    RECENT_EVENTS
+        .lock()
+        .map(|events| events.iter().cloned().collect())
+        .unwrap_or_default()
+}
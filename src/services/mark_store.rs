@@ -0,0 +1,153 @@
+use crate::config;
+use crate::services::bookmark_api::MarkData;
+use crate::services::migrations::{self, MigrationContext};
+use crate::utils::{self, Error};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::BTreeMap;
+use std::fs;
+
+// the markers snippet() puts around the matched terms, control chars never show up in page text
+pub const SNIPPET_START: &str = "\u{2}";
+pub const SNIPPET_END: &str = "\u{3}";
+/// bm25 weights of the marks_fts columns: id, title, link, domain, folder, description, content
+pub const FTS_WEIGHTS: &str = "0.0, 10.0, 2.0, 4.0, 4.0, 3.0, 1.0";
+// how many indexed words a misspelled one is widened to
+const MAX_SIMILAR_TERMS: usize = 3;
+
+/// the cached marks of every source, the mark itself is kept as json next to the columns it's queried by
+pub struct MarkStore {
+    conn: Connection,
+}
+
+impl MarkStore {
+    pub fn open() -> Result<Self, Error> {
+        let db_path = config::get_db_file_path();
+        if let Some(parent) = db_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut conn = Connection::open(db_path)?;
+        migrations::migrate(&mut conn, &MigrationContext::from_cache_dir())?;
+        Ok(MarkStore { conn })
+    }
+    /// the fallback when the cache file can't be opened, nothing outlives the run
+    pub fn open_in_memory() -> Result<Self, Error> {
+        let mut conn = Connection::open_in_memory()?;
+        migrations::migrate(&mut conn, &MigrationContext::in_memory())?;
+        Ok(MarkStore { conn })
+    }
+    pub fn conn(&self) -> &Connection {
+        &self.conn
+    }
+    pub fn get(&self, id: &str) -> Result<Option<MarkData>, Error> {
+        let data: Option<String> = self
+            .conn
+            .query_row("SELECT data FROM marks WHERE id = ?1", params![id], |row| row.get(0))
+            .optional()?;
+        match data {
+            Some(data) => Ok(Some(serde_json::from_str(&data)?)),
+            None => Ok(None),
+        }
+    }
+    pub fn save(&self, id: &str, mark: &MarkData) -> Result<(), Error> {
+        Self::insert(&self.conn, id, mark)
+    }
+    fn insert(conn: &Connection, id: &str, mark: &MarkData) -> Result<(), Error> {
+        conn.execute(
+            "INSERT OR REPLACE INTO marks (id, source_id, category_id, domain, date_added, date_last_used, title, description, data)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                id,
+                mark.source.as_ref().map(|source| source.id.clone()),
+                mark.category.id,
+                utils::domain_of(&mark.link),
+                mark.date_added,
+                mark.date_last_used,
+                mark.title,
+                mark.description,
+                serde_json::to_string(mark)?,
+            ],
+        )?;
+        conn.execute("DELETE FROM marks_fts WHERE id = ?1", params![id])?;
+        conn.execute(
+            "INSERT INTO marks_fts (id, title, link, domain, folder, description, content)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                id,
+                mark.title,
+                mark.link,
+                utils::domain_of(&mark.link),
+                mark.category.display_path(),
+                mark.description,
+                mark.content,
+            ],
+        )?;
+        Ok(())
+    }
+    pub fn delete(&self, id: &str) -> Result<(), Error> {
+        self.conn.execute("DELETE FROM marks WHERE id = ?1", params![id])?;
+        self.conn.execute("DELETE FROM marks_fts WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+    /// the indexed words at most max_distance edits away from word, the closest and most common first.
+    /// typos in the first letter are rare, only words starting with the same one are compared
+    pub fn similar_terms(&self, word: &str, max_distance: usize) -> Result<Vec<String>, Error> {
+        let word = word.to_lowercase();
+        let first = match word.chars().next() {
+            Some(first) => first,
+            None => return Ok(Vec::new()),
+        };
+        let after_first = char::from_u32(first as u32 + 1).unwrap_or(char::MAX);
+        let len = word.chars().count();
+        let mut stmt = self.conn.prepare(
+            "SELECT term, doc FROM marks_fts_vocab WHERE term >= ?1 AND term < ?2 AND length(term) BETWEEN ?3 AND ?4",
+        )?;
+        let rows = stmt
+            .query_map(
+                params![
+                    first.to_string(),
+                    after_first.to_string(),
+                    len.saturating_sub(max_distance) as i64,
+                    (len + max_distance) as i64
+                ],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+            )?
+            .collect::<Result<Vec<(String, i64)>, rusqlite::Error>>()?;
+        let mut similar: Vec<(usize, i64, String)> = rows
+            .into_iter()
+            .filter(|(term, _)| *term != word)
+            .map(|(term, docs)| (utils::levenshtein(&word, &term), docs, term))
+            .filter(|(distance, _, _)| *distance <= max_distance)
+            .collect();
+        similar.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+        Ok(similar.into_iter().take(MAX_SIMILAR_TERMS).map(|(_, _, term)| term).collect())
+    }
+    /// the marks filed in any of the categories
+    pub fn marks_in_categories(&self, category_ids: &[String]) -> Result<Vec<(String, MarkData)>, Error> {
+        let placeholders = vec!["?"; category_ids.len()].join(", ");
+        let mut stmt = self
+            .conn
+            .prepare(&format!("SELECT id, data FROM marks WHERE category_id IN ({})", placeholders))?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(category_ids.iter()), |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<(String, String)>, rusqlite::Error>>()?;
+        let mut marks = Vec::new();
+        for (id, data) in rows.into_iter() {
+            marks.push((id, serde_json::from_str(&data)?));
+        }
+        Ok(marks)
+    }
+    /// every mark cached for a source, keyed by bookmark id
+    pub fn marks_for_source(&self, source_id: &str) -> Result<BTreeMap<String, MarkData>, Error> {
+        let mut stmt = self.conn.prepare("SELECT id, data FROM marks WHERE source_id = ?1")?;
+        let rows = stmt
+            .query_map(params![source_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<(String, String)>, rusqlite::Error>>()?;
+        let mut marks = BTreeMap::new();
+        for (id, data) in rows.into_iter() {
+            marks.insert(id, serde_json::from_str(&data)?);
+        }
+        Ok(marks)
+    }
+}
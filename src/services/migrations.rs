@@ -0,0 +1,431 @@
+use crate::config;
+use crate::services::bookmark_api::MarkData;
+use crate::services::chromium::ChromiumBrowser;
+use crate::services::source::SourceInfo;
+use crate::utils::{self, Error, ErrorKind};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+// the jfs files are renamed once their marks are in the db, so they are never imported twice
+const MIGRATED_SUFFIX: &str = "migrated";
+
+/// one step of the cache schema, the db's PRAGMA user_version is the last step it went through
+struct Migration {
+    version: u32,
+    description: &'static str,
+    up: fn(&Transaction, &MigrationContext) -> Result<(), Error>,
+}
+
+/// what the steps need from outside the db
+pub struct MigrationContext {
+    /// the old jfs caches to import, left alone when the db doesn't outlive the run
+    pub jfs_files: Vec<PathBuf>,
+}
+
+impl MigrationContext {
+    /// the legacy single file and one per library in the cache dir
+    pub fn from_cache_dir() -> Self {
+        let mut jfs_files: Vec<PathBuf> = vec![config::get_cache_file_path()];
+        if let Ok(entries) = fs::read_dir(config::get_libraries_dir()) {
+            jfs_files.extend(
+                entries.filter_map(|entry| entry.ok().map(|entry| entry.path().join(config::CACHE_FILE_PATH))),
+            );
+        }
+        MigrationContext { jfs_files }
+    }
+    /// an in-memory db imports nothing, so the jfs files are still there for the next run
+    pub fn in_memory() -> Self {
+        MigrationContext { jfs_files: Vec::new() }
+    }
+}
+
+/// append only: a released step is never edited, a change to the cache gets a new step
+const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, description: "create the marks table", up: create_marks_table },
+    Migration { version: 2, description: "import the jfs json caches", up: import_jfs_files },
+    Migration { version: 3, description: "fill in the folder path of old marks", up: fill_category_paths },
+    Migration { version: 4, description: "create the full text search index", up: create_search_index },
+    Migration { version: 5, description: "expose the search index vocabulary", up: create_search_vocabulary },
+    Migration { version: 6, description: "give the legacy marks their chrome source", up: assign_legacy_source },
+];
+
+pub fn schema_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+fn user_version(conn: &Connection) -> Result<u32, Error> {
+    Ok(conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+}
+
+/// brings the db up to the current schema, each step runs in its own transaction
+/// together with the version bump so a failed step is retried on the next start
+pub fn migrate(conn: &mut Connection, context: &MigrationContext) -> Result<(), Error> {
+    let current = user_version(conn)?;
+    if current > schema_version() {
+        return Err(Error::new(
+            ErrorKind::Cache,
+            format!(
+                "the marks cache has schema version {} but this build only knows up to {}",
+                current,
+                schema_version()
+            ),
+        ));
+    }
+    for migration in MIGRATIONS.iter().filter(|migration| migration.version > current) {
+        log::info!("migrating the marks cache version={} step=\"{}\"", migration.version, migration.description);
+        let tx = conn.transaction()?;
+        (migration.up)(&tx, context)?;
+        // pragmas don't take bound parameters
+        tx.execute_batch(&format!("PRAGMA user_version = {}", migration.version))?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// rewrites the json of every cached mark, for changes in the shape of MarkData
+fn upgrade_records(tx: &Transaction, upgrade: fn(&mut Value)) -> Result<(), Error> {
+    let rows = {
+        let mut stmt = tx.prepare("SELECT id, data FROM marks")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<(String, String)>, rusqlite::Error>>()?;
+        rows
+    };
+    for (id, data) in rows.into_iter() {
+        let mut record: Value = serde_json::from_str(&data)?;
+        upgrade(&mut record);
+        tx.execute("UPDATE marks SET data = ?1 WHERE id = ?2", params![serde_json::to_string(&record)?, id])?;
+    }
+    Ok(())
+}
+
+fn create_marks_table(tx: &Transaction, _context: &MigrationContext) -> Result<(), Error> {
+    // "if not exists" keeps the caches made before the schema was versioned
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS marks (
+            id TEXT PRIMARY KEY,
+            source_id TEXT,
+            category_id TEXT NOT NULL,
+            domain TEXT NOT NULL,
+            date_added INTEGER,
+            date_last_used INTEGER,
+            title TEXT NOT NULL,
+            description TEXT NOT NULL,
+            data TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS marks_source_id ON marks (source_id);
+        CREATE INDEX IF NOT EXISTS marks_category_id ON marks (category_id);
+        CREATE INDEX IF NOT EXISTS marks_domain ON marks (domain);
+        CREATE INDEX IF NOT EXISTS marks_date_added ON marks (date_added);
+        CREATE INDEX IF NOT EXISTS marks_date_last_used ON marks (date_last_used);",
+    )?;
+    Ok(())
+}
+
+/// marks_fts holds the searchable text of every mark, kept in step with marks by MarkStore
+fn create_search_index(tx: &Transaction, _context: &MigrationContext) -> Result<(), Error> {
+    tx.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS marks_fts USING fts5(
+            id UNINDEXED,
+            title,
+            link,
+            domain,
+            folder,
+            description,
+            content,
+            tokenize = 'porter unicode61 remove_diacritics 2'
+        );
+        INSERT INTO marks_fts (id, title, link, domain, folder, description, content)
+        SELECT
+            id,
+            title,
+            json_extract(data, '$.link'),
+            domain,
+            json_extract(data, '$.category.path'),
+            description,
+            json_extract(data, '$.content')
+        FROM marks;",
+    )?;
+    Ok(())
+}
+
+/// the indexed words, where the search looks for the words a typo was meant to be
+fn create_search_vocabulary(tx: &Transaction, _context: &MigrationContext) -> Result<(), Error> {
+    tx.execute_batch("CREATE VIRTUAL TABLE IF NOT EXISTS marks_fts_vocab USING fts5vocab(marks_fts, row);")?;
+    Ok(())
+}
+
+/// the only profile read before there were sources, the marks of the legacy cache come from it
+fn legacy_source() -> SourceInfo {
+    let browser = ChromiumBrowser::Chrome;
+    SourceInfo::new(browser.key(), browser.name(), config::CHROMIUM_DEFAULT_PROFILE, config::CHROMIUM_DEFAULT_PROFILE)
+}
+
+/// a mark cached before sources existed gets the id and source the sync gives its bookmark now,
+/// so the next sync finds it instead of scraping the page again
+fn with_source(id: &str, mut mark: MarkData) -> (String, MarkData) {
+    if mark.source.is_some() {
+        return (id.to_string(), mark);
+    }
+    let source = legacy_source();
+    let id = format!("{}_{}", source.id, id);
+    mark.source = Some(source);
+    (id, mark)
+}
+
+/// moves the marks of the old jfs cache files into the db. a file that can't be parsed fails the
+/// step, so nothing is renamed and the import is tried again on the next start
+fn import_jfs_files(tx: &Transaction, context: &MigrationContext) -> Result<(), Error> {
+    let jfs_files = &context.jfs_files;
+    for jfs_file in jfs_files.iter().filter(|path| path.is_file()) {
+        let marks: BTreeMap<String, MarkData> = serde_json::from_str(&fs::read_to_string(jfs_file)?)
+            .map_err(|error| {
+                Error::new(ErrorKind::Parse, format!("the old cache {} can't be read: {}", jfs_file.display(), error))
+            })?;
+        for (id, mark) in marks.into_iter().map(|(id, mark)| with_source(&id, mark)) {
+            let exists: Option<String> = tx
+                .query_row("SELECT id FROM marks WHERE id = ?1", params![&id], |row| row.get(0))
+                .optional()?;
+            // a mark that was synced again since wins over its old copy
+            if exists.is_none() {
+                // the columns of version 1, later steps fill in what they add
+                tx.execute(
+                    "INSERT INTO marks (id, source_id, category_id, domain, date_added, date_last_used, title, description, data)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![
+                        id,
+                        mark.source.as_ref().map(|source| source.id.clone()),
+                        mark.category.id,
+                        utils::domain_of(&mark.link),
+                        mark.date_added,
+                        mark.date_last_used,
+                        mark.title,
+                        mark.description,
+                        serde_json::to_string(&mark)?,
+                    ],
+                )?;
+            }
+        }
+    }
+    // only renamed once every file made it in, the transaction is rolled back otherwise
+    for jfs_file in jfs_files.iter().filter(|path| path.is_file()) {
+        fs::rename(jfs_file, jfs_file.with_extension(MIGRATED_SUFFIX))?;
+    }
+    Ok(())
+}
+
+/// marks cached before folder paths existed only know their folder name
+fn fill_category_paths(tx: &Transaction, _context: &MigrationContext) -> Result<(), Error> {
+    upgrade_records(tx, |record| {
+        if let Some(category) = record.get_mut("category").and_then(|category| category.as_object_mut()) {
+            let path_missing = category.get("path").and_then(|path| path.as_str()).map_or(true, |path| path.is_empty());
+            if path_missing {
+                let name = category.get("name").cloned().unwrap_or(Value::String(String::new()));
+                category.insert(String::from("path"), name);
+            }
+        }
+    })
+}
+
+/// the marks of the legacy cache were imported without a source, no source query could see them
+fn assign_legacy_source(tx: &Transaction, _context: &MigrationContext) -> Result<(), Error> {
+    let rows = {
+        let mut stmt = tx.prepare("SELECT id, data FROM marks WHERE source_id IS NULL")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<(String, String)>, rusqlite::Error>>()?;
+        rows
+    };
+    for (old_id, data) in rows.into_iter() {
+        let (id, mark) = with_source(&old_id, serde_json::from_str(&data)?);
+        let taken: Option<String> = tx
+            .query_row("SELECT id FROM marks WHERE id = ?1", params![&id], |row| row.get(0))
+            .optional()?;
+        // the bookmark was synced again since, its old copy is dropped
+        if taken.is_some() {
+            tx.execute("DELETE FROM marks WHERE id = ?1", params![&old_id])?;
+            tx.execute("DELETE FROM marks_fts WHERE id = ?1", params![&old_id])?;
+            continue;
+        }
+        tx.execute(
+            "UPDATE marks SET id = ?1, source_id = ?2, data = ?3 WHERE id = ?4",
+            params![&id, mark.source.as_ref().map(|source| source.id.clone()), serde_json::to_string(&mark)?, &old_id],
+        )?;
+        tx.execute("UPDATE marks_fts SET id = ?1 WHERE id = ?2", params![&id, &old_id])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// a mark the way the jfs caches and the first db versions stored it, without a folder path
+    fn old_mark(title: &str, folder: &str, source_id: Option<&str>) -> Value {
+        let mut mark = json!({
+            "title": title,
+            "description": "",
+            "content": "the borrow checker explained",
+            "image": "default.png",
+            "image_data": null,
+            "category": { "name": folder, "id": "7" },
+            "link": "https://doc.rust-lang.org/book/",
+        });
+        if let Some(source_id) = source_id {
+            mark["source"] = json!({ "id": source_id, "browser": "Firefox", "profile": "default" });
+        }
+        mark
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("roboza-migrations-{}-{}", std::process::id(), name));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// saves a mark the way a db at version did, with its search index row from version 4 on
+    fn save_old_mark(tx: &Transaction, version: u32, id: &str, source_id: Option<&str>, mark: &Value) {
+        tx.execute(
+            "INSERT INTO marks (id, source_id, category_id, domain, date_added, date_last_used, title, description, data)
+             VALUES (?1, ?2, '7', 'doc.rust-lang.org', NULL, NULL, ?3, '', ?4)",
+            params![id, source_id, mark["title"].as_str(), mark.to_string()],
+        )
+        .unwrap();
+        if version >= 4 {
+            tx.execute(
+                "INSERT INTO marks_fts (id, title, link, domain, folder, description, content)
+                 VALUES (?1, ?2, ?3, 'doc.rust-lang.org', ?4, '', ?5)",
+                params![
+                    id,
+                    mark["title"].as_str(),
+                    mark["link"].as_str(),
+                    mark["category"]["path"].as_str(),
+                    mark["content"].as_str()
+                ],
+            )
+            .unwrap();
+        }
+    }
+
+    /// a db left at version, with the marks saved the way that version saved them: a synced one
+    /// and, from the jfs import of version 2 on, a legacy one without a source
+    fn fixture(version: u32) -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let tx = conn.transaction().unwrap();
+        for migration in MIGRATIONS.iter().filter(|migration| migration.version <= version) {
+            (migration.up)(&tx, &MigrationContext::in_memory()).unwrap();
+        }
+        let mut mark = old_mark("The Rust Book", "Rust", Some("firefox-default"));
+        let mut legacy_mark = old_mark("Tokio", "Async", None);
+        // version 3 filled in the paths, the marks saved after it have one
+        if version >= 3 {
+            mark["category"]["path"] = json!("Bookmarks bar › Rust");
+            legacy_mark["category"]["path"] = json!("Async");
+        }
+        save_old_mark(&tx, version, "firefox-default_1", Some("firefox-default"), &mark);
+        if version >= 2 {
+            save_old_mark(&tx, version, "abc", None, &legacy_mark);
+        }
+        tx.execute_batch(&format!("PRAGMA user_version = {}", version)).unwrap();
+        tx.commit().unwrap();
+        conn
+    }
+
+    fn search_hits(conn: &Connection, query: &str) -> i64 {
+        conn.query_row("SELECT count(*) FROM marks_fts WHERE marks_fts MATCH ?1", params![query], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn category_path(conn: &Connection, id: &str) -> String {
+        conn.query_row("SELECT json_extract(data, '$.category.path') FROM marks WHERE id = ?1", params![id], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn indexed_folder(conn: &Connection, id: &str) -> Option<String> {
+        conn.query_row("SELECT folder FROM marks_fts WHERE id = ?1", params![id], |row| row.get(0))
+            .optional()
+            .unwrap()
+    }
+
+    #[test]
+    fn upgrades_the_jfs_caches() {
+        let dir = temp_dir("v0");
+        let legacy = dir.join("robozaPress.json");
+        let library = dir.join("library.json");
+        fs::write(&legacy, json!({ "abc": old_mark("The Rust Book", "Rust", None) }).to_string()).unwrap();
+        fs::write(&library, json!({ "firefox-default_2": old_mark("Tokio", "Async", Some("firefox-default")) }).to_string()).unwrap();
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn, &MigrationContext { jfs_files: vec![legacy.clone(), library.clone()] }).unwrap();
+
+        assert_eq!(user_version(&conn).unwrap(), schema_version());
+        // the legacy marks belong to the chrome profile that was the only one read back then
+        let source_id: String = conn
+            .query_row("SELECT source_id FROM marks WHERE id = 'chrome-default_abc'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(source_id, "chrome-default");
+        assert_eq!(category_path(&conn, "chrome-default_abc"), "Rust");
+        assert_eq!(category_path(&conn, "firefox-default_2"), "Async");
+        assert_eq!(indexed_folder(&conn, "chrome-default_abc").as_deref(), Some("Rust"));
+        assert_eq!(indexed_folder(&conn, "firefox-default_2").as_deref(), Some("Async"));
+        assert!(!legacy.exists() && legacy.with_extension(MIGRATED_SUFFIX).exists());
+        assert!(!library.exists() && library.with_extension(MIGRATED_SUFFIX).exists());
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn keeps_a_corrupt_jfs_cache() {
+        let dir = temp_dir("corrupt");
+        let legacy = dir.join("robozaPress.json");
+        fs::write(&legacy, "{ not json").unwrap();
+        let mut conn = Connection::open_in_memory().unwrap();
+        let result = migrate(&mut conn, &MigrationContext { jfs_files: vec![legacy.clone()] });
+
+        assert_eq!(result.unwrap_err().kind, ErrorKind::Parse);
+        assert!(legacy.exists());
+        // the steps before the failed one are kept
+        assert_eq!(user_version(&conn).unwrap(), 1);
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn upgrades_every_schema_version() {
+        for version in 1..schema_version() {
+            let mut conn = fixture(version);
+            migrate(&mut conn, &MigrationContext::in_memory()).unwrap();
+
+            let expected_path = if version >= 3 { "Bookmarks bar › Rust" } else { "Rust" };
+            assert_eq!(user_version(&conn).unwrap(), schema_version(), "from version {}", version);
+            assert_eq!(category_path(&conn, "firefox-default_1"), expected_path, "from version {}", version);
+            assert_eq!(indexed_folder(&conn, "firefox-default_1").as_deref(), Some(expected_path), "from version {}", version);
+            let marks = if version >= 2 { 2 } else { 1 };
+            assert_eq!(search_hits(&conn, "borrow"), marks, "from version {}", version);
+            let vocabulary: i64 = conn
+                .query_row("SELECT count(*) FROM marks_fts_vocab WHERE term = 'borrow'", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(vocabulary, 1, "from version {}", version);
+            if version >= 2 {
+                // the legacy mark moved to its chrome id, in the index too
+                let source_id: String = conn
+                    .query_row("SELECT source_id FROM marks WHERE id = 'chrome-default_abc'", [], |row| row.get(0))
+                    .unwrap();
+                assert_eq!(source_id, "chrome-default", "from version {}", version);
+                assert_eq!(category_path(&conn, "chrome-default_abc"), "Async", "from version {}", version);
+                assert_eq!(indexed_folder(&conn, "chrome-default_abc").as_deref(), Some("Async"), "from version {}", version);
+                assert_eq!(indexed_folder(&conn, "abc"), None, "from version {}", version);
+            }
+        }
+    }
+
+    #[test]
+    fn refuses_a_newer_cache() {
+        let mut conn = fixture(schema_version());
+        conn.execute_batch(&format!("PRAGMA user_version = {}", schema_version() + 1)).unwrap();
+
+        assert_eq!(migrate(&mut conn, &MigrationContext::in_memory()).unwrap_err().kind, ErrorKind::Cache);
+    }
+}
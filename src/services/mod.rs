@@ -1,2 +1,15 @@
 pub mod bookmark_api;
-pub mod asset;
\ No newline at end of file
+pub mod asset;
+pub mod firefox;
+pub mod source;
+pub mod chromium;
+pub mod netscape;
+pub mod sync;
+pub mod mark_store;
+pub mod migrations;
+pub mod query;
+pub mod page_meta;
+pub mod readability;
+pub mod archive;
+pub mod health;
+pub mod logging;
@@ -0,0 +1,363 @@
+use crate::services::bookmark_api::{BookmarkBar, BookmarkCategory, BookmarkFile, BookmarkType, BookmarksItem, BookmarksRoot, MarkData};
+use crate::services::source::{BookmarkSource, SourceError, SourceInfo};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+static TOKEN_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)<dl[^>]*>|</dl\s*>|<h3([^>]*)>(.*?)</h3\s*>|<a\s([^>]*)>(.*?)</a\s*>").unwrap()
+});
+static ATTR_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(?is)([a-z_-]+)\s*=\s*"([^"]*)""#).unwrap());
+static TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+
+// netscape files count seconds since 1970, chrome counts microseconds since 1601
+const WINDOWS_TO_UNIX_EPOCH_SECS: i64 = 11_644_473_600;
+
+struct Frame {
+    folder: Option<BookmarksItem>,
+    children: Vec<BookmarksItem>,
+}
+
+fn decode_entities(val: &str) -> String {
+    val.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+fn encode_entities(val: &str) -> String {
+    val.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn parse_attrs(raw: &str) -> HashMap<String, String> {
+    ATTR_RE
+        .captures_iter(raw)
+        .map(|cap| (cap[1].to_uppercase(), decode_entities(&cap[2])))
+        .collect()
+}
+
+fn to_chrome_date(add_date: Option<&String>) -> String {
+    add_date
+        .and_then(|date| date.trim().parse::<i64>().ok())
+        .map(|secs| ((secs + WINDOWS_TO_UNIX_EPOCH_SECS) * 1_000_000).to_string())
+        .unwrap_or_default()
+}
+
+fn parse_tags(tags: &str) -> Vec<String> {
+    tags.split(',')
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+fn inner_text(raw: &str) -> String {
+    decode_entities(TAG_RE.replace_all(raw, "").trim())
+}
+
+/// parses the nested <DL><DT><H3> folders and <A HREF ADD_DATE LAST_VISIT ICON TAGS> entries into a bookmarks tree
+pub fn parse(html: &str) -> Vec<BookmarksItem> {
+    let mut next_id: usize = 0;
+    let mut stack: Vec<Frame> = vec![Frame { folder: None, children: Vec::new() }];
+    // an <H3> only becomes a parent once its <DL> opens, empty folders never get one
+    let mut pending_folder: Option<BookmarksItem> = None;
+    for cap in TOKEN_RE.captures_iter(html) {
+        let token = cap.get(0).unwrap().as_str();
+        let lower_token = token.to_lowercase();
+        if lower_token.starts_with("<dl") {
+            stack.push(Frame { folder: pending_folder.take(), children: Vec::new() });
+            continue;
+        }
+        if let Some(folder) = pending_folder.take() {
+            stack.last_mut().unwrap().children.push(folder);
+        }
+        if lower_token.starts_with("</dl") {
+            if stack.len() > 1 {
+                let frame = stack.pop().unwrap();
+                let parent = stack.last_mut().unwrap();
+                match frame.folder {
+                    Some(mut folder) => {
+                        folder.children = Some(frame.children);
+                        parent.children.push(folder);
+                    }
+                    None => parent.children.extend(frame.children),
+                }
+            }
+        } else if let Some(h3_attrs) = cap.get(1) {
+            next_id += 1;
+            let attrs = parse_attrs(h3_attrs.as_str());
+            pending_folder = Some(BookmarksItem {
+                date_added: to_chrome_date(attrs.get("ADD_DATE")),
+                date_last_used: None,
+                guid: String::new(),
+                id: next_id.to_string(),
+                name: inner_text(cap.get(2).map_or("", |m| m.as_str())),
+                r#type: BookmarkType::folder,
+                url: None,
+                children: Some(Vec::new()),
+                icon: None,
+                tags: Vec::new(),
+                source: None,
+            });
+        } else if let Some(a_attrs) = cap.get(3) {
+            let attrs = parse_attrs(a_attrs.as_str());
+            let url = match attrs.get("HREF") {
+                Some(url) if !url.is_empty() => url.clone(),
+                _ => continue,
+            };
+            next_id += 1;
+            let name = inner_text(cap.get(4).map_or("", |m| m.as_str()));
+            stack.last_mut().unwrap().children.push(BookmarksItem {
+                date_added: to_chrome_date(attrs.get("ADD_DATE")),
+                date_last_used: attrs.get("LAST_VISIT").map(|date| to_chrome_date(Some(date))).filter(|date| !date.is_empty()),
+                guid: String::new(),
+                id: next_id.to_string(),
+                name: if name.is_empty() { url.clone() } else { name },
+                r#type: BookmarkType::url,
+                url: Some(url),
+                children: None,
+                icon: attrs.get("ICON").cloned(),
+                tags: attrs.get("TAGS").map(|tags| parse_tags(tags)).unwrap_or_default(),
+                source: None,
+            });
+        }
+    }
+    if let Some(folder) = pending_folder.take() {
+        stack.last_mut().unwrap().children.push(folder);
+    }
+    // close whatever the file left open
+    while stack.len() > 1 {
+        let frame = stack.pop().unwrap();
+        let parent = stack.last_mut().unwrap();
+        match frame.folder {
+            Some(mut folder) => {
+                folder.children = Some(frame.children);
+                parent.children.push(folder);
+            }
+            None => parent.children.extend(frame.children),
+        }
+    }
+    stack.pop().map(|frame| frame.children).unwrap_or_default()
+}
+
+fn export_mark(mark: &MarkData, indent: &str) -> String {
+    let mut attrs = format!("HREF=\"{}\"", encode_entities(&mark.link));
+    if let Some(date_added) = mark.date_added {
+        attrs.push_str(&format!(" ADD_DATE=\"{}\"", date_added));
+    }
+    if let Some(date_last_used) = mark.date_last_used {
+        attrs.push_str(&format!(" LAST_VISIT=\"{}\"", date_last_used));
+    }
+    if !mark.tags.is_empty() {
+        attrs.push_str(&format!(" TAGS=\"{}\"", encode_entities(&mark.tags.join(","))));
+    }
+    format!("{}<DT><A {}>{}</A>\n", indent, attrs, encode_entities(&mark.title))
+}
+
+/// the folders to export by their parent, and the marks by their folder
+struct FolderTree<'a> {
+    subfolders: HashMap<Option<&'a str>, Vec<&'a BookmarkCategory>>,
+    marks: HashMap<&'a str, Vec<&'a MarkData>>,
+}
+
+impl<'a> FolderTree<'a> {
+    /// folders without a mark anywhere below them are left out
+    fn has_marks(&self, folder_id: &str) -> bool {
+        self.marks.contains_key(folder_id)
+            || self
+                .subfolders
+                .get(&Some(folder_id))
+                .map_or(false, |subfolders| subfolders.iter().any(|folder| self.has_marks(&folder.id)))
+    }
+    fn write(&self, html: &mut String, parent_id: Option<&'a str>, depth: usize) {
+        let indent = "    ".repeat(depth);
+        for folder in self.subfolders.get(&parent_id).into_iter().flatten().copied() {
+            if !self.has_marks(&folder.id) {
+                continue;
+            }
+            html.push_str(&format!("{}<DT><H3>{}</H3>\n{}<DL><p>\n", indent, encode_entities(&folder.name), indent));
+            self.write(html, Some(folder.id.as_str()), depth + 1);
+            for mark in self.marks.get(folder.id.as_str()).into_iter().flatten() {
+                html.push_str(&export_mark(mark, &"    ".repeat(depth + 1)));
+            }
+            html.push_str(&format!("{}</DL><p>\n", indent));
+        }
+    }
+}
+
+/// writes the cached library as a netscape bookmarks file, the categories become nested folders
+/// in the order they are given, parents before their subfolders
+pub fn export(marks: &Vec<MarkData>, categories: &Vec<BookmarkCategory>) -> String {
+    let default_category = BookmarkCategory::default();
+    let mut tree = FolderTree { subfolders: HashMap::new(), marks: HashMap::new() };
+    let mut root_marks: Vec<&MarkData> = Vec::new();
+    for mark in marks.iter() {
+        if mark.category.id == default_category.id {
+            root_marks.push(mark);
+        } else {
+            tree.marks.entry(mark.category.id.as_str()).or_default().push(mark);
+        }
+    }
+    // a mark can still be filed under a folder the library no longer has
+    let mut folders: Vec<&BookmarkCategory> = categories.iter().filter(|cat| cat.id != default_category.id).collect();
+    let mut folder_ids: HashSet<&str> = folders.iter().map(|cat| cat.id.as_str()).collect();
+    for mark in marks.iter() {
+        if mark.category.id != default_category.id && folder_ids.insert(mark.category.id.as_str()) {
+            folders.push(&mark.category);
+        }
+    }
+    for folder in folders.into_iter() {
+        let parent_id = folder.parent_id.as_deref().filter(|parent_id| folder_ids.contains(parent_id));
+        tree.subfolders.entry(parent_id).or_default().push(folder);
+    }
+    let mut html = String::from(
+        "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n\
+         <META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n\
+         <TITLE>Bookmarks</TITLE>\n\
+         <H1>Bookmarks</H1>\n\
+         <DL><p>\n",
+    );
+    tree.write(&mut html, None, 1);
+    for mark in root_marks.iter() {
+        html.push_str(&export_mark(mark, "    "));
+    }
+    html.push_str("</DL><p>\n");
+    html
+}
+
+/// an imported bookmarks.html kept in the cache imports dir
+pub struct NetscapeSource {
+    path: PathBuf,
+}
+
+impl NetscapeSource {
+    pub const BROWSER: &str = "Imported HTML";
+    pub fn new(path: PathBuf) -> Self {
+        NetscapeSource { path }
+    }
+}
+
+impl BookmarkSource for NetscapeSource {
+    fn info(&self) -> SourceInfo {
+        let name = self
+            .path
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        SourceInfo::new("html", Self::BROWSER, &name, &name)
+    }
+    fn read_bookmark_file(&self) -> Result<BookmarkFile, SourceError> {
+        let html = fs::read_to_string(&self.path).map_err(|error| SourceError::from_io(&self.path, error))?;
+        Ok(BookmarkFile {
+            checksum: String::new(),
+            roots: BookmarksRoot {
+                bookmark_bar: BookmarkBar { id: None, name: None, children: Some(parse(&html)) },
+                other: BookmarkBar { id: None, name: None, children: Some(Vec::new()) },
+                synced: BookmarkBar { id: None, name: None, children: Some(Vec::new()) },
+            },
+        })
+    }
+    fn watch_paths(&self) -> Vec<PathBuf> {
+        vec![self.path.clone()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::bookmark_api::LinkStatus;
+    use crate::services::page_meta::PageMeta;
+
+    const NESTED: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<DL><p>
+    <DT><H3>Dev</H3>
+    <DL><p>
+        <DT><H3>Rust</H3>
+        <DL><p>
+            <DT><A HREF="https://doc.rust-lang.org/book/" ADD_DATE="1600000000">The Rust Book</A>
+            <DT><H3>Async</H3>
+            <DL><p>
+                <DT><A HREF="https://tokio.rs/">Tokio</A>
+            </DL><p>
+        </DL><p>
+        <DT><H3>Empty</H3>
+        <DL><p>
+        </DL><p>
+        <DT><A HREF="https://github.com/">GitHub</A>
+    </DL><p>
+    <DT><H3>News &amp; Blogs</H3>
+    <DL><p>
+        <DT><A HREF="https://news.ycombinator.com/" TAGS="tech,news">Hacker News</A>
+    </DL><p>
+    <DT><A HREF="https://example.com/">At the top</A>
+</DL><p>
+"#;
+
+    /// the categories and the marks filed in them, the way a sync makes them
+    fn library(html: &str) -> (Vec<MarkData>, Vec<BookmarkCategory>) {
+        let items = parse(html);
+        let mut categories: Vec<BookmarkCategory> = Vec::new();
+        BookmarksItem::collect_categories(&items, None, &mut categories);
+        let mut filed: Vec<(BookmarksItem, BookmarkCategory)> = Vec::new();
+        BookmarksItem::collect_marks(&items, &BookmarkCategory::default(), &categories, &mut filed);
+        let marks = filed
+            .into_iter()
+            .map(|(item, category)| MarkData {
+                title: item.name.clone(),
+                description: String::new(),
+                content: String::new(),
+                image: String::new(),
+                image_data: None,
+                category,
+                link: item.url.clone().unwrap_or_default(),
+                source: None,
+                link_status: LinkStatus::Unknown,
+                tags: item.tags.clone(),
+                meta: PageMeta::default(),
+                archive: None,
+                health: None,
+                snippet: None,
+                id: None,
+                score: None,
+                date_added: None,
+                date_last_used: None,
+            })
+            .collect();
+        (marks, categories)
+    }
+
+    /// the folder path of every mark, by link
+    fn mark_paths(marks: &Vec<MarkData>) -> Vec<(String, String)> {
+        let mut paths: Vec<(String, String)> = marks
+            .iter()
+            .map(|mark| (mark.link.clone(), mark.category.display_path().to_string()))
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn keeps_the_folder_tree_through_an_export() {
+        let (marks, categories) = library(NESTED);
+        let (exported_marks, exported_categories) = library(&export(&marks, &categories));
+        assert_eq!(mark_paths(&exported_marks), mark_paths(&marks));
+        let paths: Vec<&str> = exported_categories.iter().map(|cat| cat.path.as_str()).collect();
+        assert_eq!(paths, vec!["Dev", "Dev › Rust", "Dev › Rust › Async", "News & Blogs"]);
+        let tagged = exported_marks.iter().find(|mark| mark.link == "https://news.ycombinator.com/").unwrap();
+        assert_eq!(tagged.tags, vec![String::from("tech"), String::from("news")]);
+    }
+
+    #[test]
+    fn exports_the_folders_of_marks_the_library_lost() {
+        let (marks, _) = library(NESTED);
+        let (exported_marks, _) = library(&export(&marks, &Vec::new()));
+        let rust_book = exported_marks.iter().find(|mark| mark.link == "https://doc.rust-lang.org/book/").unwrap();
+        assert_eq!(rust_book.category.name, "Rust");
+        assert_eq!(exported_marks.len(), marks.len());
+    }
+}
@@ -0,0 +1,71 @@
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+
+/// what a page says about itself in its head, read while the bookmark is scraped
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct PageMeta {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub canonical_url: Option<String>,
+    pub site_name: Option<String>,
+    pub author: Option<String>,
+    /// unix seconds
+    pub published: Option<i64>,
+    pub lang: Option<String>,
+}
+
+/// the content of the first of the meta tags that is there and not empty, in order of preference.
+/// open graph tags use "property", twitter and the plain ones "name"
+fn meta_content(html: &Html, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|key| {
+        let selector = Selector::parse(&format!("meta[property=\"{key}\"], meta[name=\"{key}\"]", key = key)).ok()?;
+        html.select(&selector)
+            .filter_map(|element| element.value().attr("content"))
+            .map(|content| content.split_whitespace().collect::<Vec<&str>>().join(" "))
+            .find(|content| !content.is_empty())
+    })
+}
+
+fn first_attr(html: &Html, selector: &str, attr: &str) -> Option<String> {
+    let selector = Selector::parse(selector).ok()?;
+    html.select(&selector)
+        .filter_map(|element| element.value().attr(attr))
+        .map(|val| val.trim().to_string())
+        .find(|val| !val.is_empty())
+}
+
+fn first_text(html: &Html, selector: &str) -> Option<String> {
+    let selector = Selector::parse(selector).ok()?;
+    html.select(&selector)
+        .map(|element| element.text().collect::<Vec<&str>>().join(" ").split_whitespace().collect::<Vec<&str>>().join(" "))
+        .find(|text| !text.is_empty())
+}
+
+/// publish dates come as full rfc 3339 timestamps or as plain days
+fn parse_published(val: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(val.trim())
+        .map(|date| date.timestamp())
+        .ok()
+        .or_else(|| {
+            chrono::NaiveDate::parse_from_str(val.trim().get(..10)?, "%Y-%m-%d")
+                .ok()?
+                .and_hms_opt(0, 0, 0)
+                .map(|date| date.and_utc().timestamp())
+        })
+}
+
+pub fn extract(html: &Html) -> PageMeta {
+    PageMeta {
+        title: meta_content(html, &["og:title", "twitter:title"]).or_else(|| first_text(html, "head title")),
+        description: meta_content(html, &["og:description", "twitter:description", "description"]),
+        canonical_url: first_attr(html, "link[rel=\"canonical\"]", "href").or_else(|| meta_content(html, &["og:url"])),
+        site_name: meta_content(html, &["og:site_name", "application-name", "twitter:site"]),
+        author: meta_content(html, &["author", "article:author", "twitter:creator"]),
+        published: meta_content(html, &["article:published_time", "date", "dc.date", "pubdate"])
+            .or_else(|| first_attr(html, "[itemprop=\"datePublished\"]", "content"))
+            .and_then(|date| parse_published(&date)),
+        lang: first_attr(html, "html[lang]", "lang")
+            .or_else(|| meta_content(html, &["og:locale"]))
+            .map(|lang| lang.replace('_', "-")),
+    }
+}
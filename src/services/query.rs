@@ -0,0 +1,591 @@
+use crate::services::bookmark_api::{LinkStatus, CATEGORY_PATH_SEPARATOR};
+use crate::utils;
+use regex::{Regex, RegexBuilder};
+use rusqlite::types::Value;
+use std::fmt;
+use std::str::FromStr;
+
+// shorter words have too many neighbours to guess which one was meant
+const MIN_TYPO_WORD_LEN: usize = 4;
+const LONG_WORD_LEN: usize = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    pub message: String,
+    /// the char the problem starts at
+    pub position: usize,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at character {})", self.message, self.position + 1)
+    }
+}
+
+impl QueryError {
+    fn new(message: &str, position: usize) -> Self {
+        QueryError { message: message.to_string(), position }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateCmp {
+    Before,
+    BeforeOrOn,
+    After,
+    AfterOrOn,
+    On,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TermKind {
+    Word(String),
+    Phrase(String),
+    Site(String),
+    /// the folder names of an in:"Rust/Async" path
+    In(Vec<String>),
+    Tag(String),
+    Added(DateCmp, String),
+    Status(LinkStatus),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub negated: bool,
+    pub kind: TermKind,
+    /// indexed words close to a misspelled word, searched along with it
+    pub alternatives: Vec<String>,
+}
+
+/// a parsed search box: every clause has to match, a clause matches when one of its OR'ed terms does
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SearchQuery {
+    pub clauses: Vec<Vec<Term>>,
+}
+
+/// the filters a word can start with, e.g. site:github.com
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Site,
+    In,
+    Tag,
+    Added,
+    Status,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Field> {
+        match name.to_lowercase().as_str() {
+            "site" => Some(Field::Site),
+            "in" => Some(Field::In),
+            "tag" => Some(Field::Tag),
+            "added" => Some(Field::Added),
+            "status" => Some(Field::Status),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Field::Site => write!(f, "site"),
+            Field::In => write!(f, "in"),
+            Field::Tag => write!(f, "tag"),
+            Field::Added => write!(f, "added"),
+            Field::Status => write!(f, "status"),
+        }
+    }
+}
+
+enum Token {
+    Or,
+    Term(Term),
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Lexer {
+    fn new(input: &str) -> Self {
+        Lexer { chars: input.chars().collect(), pos: 0 }
+    }
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+    }
+    fn read_quoted(&mut self) -> Result<String, QueryError> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut value = String::new();
+        while self.pos < self.chars.len() && self.chars[self.pos] != '"' {
+            value.push(self.chars[self.pos]);
+            self.pos += 1;
+        }
+        if self.pos >= self.chars.len() {
+            return Err(QueryError::new("this quote is never closed", start));
+        }
+        self.pos += 1;
+        Ok(value)
+    }
+    fn read_bare(&mut self) -> String {
+        let mut value = String::new();
+        while self.pos < self.chars.len() && !self.chars[self.pos].is_whitespace() && self.chars[self.pos] != '"' {
+            value.push(self.chars[self.pos]);
+            self.pos += 1;
+        }
+        value
+    }
+    fn next_token(&mut self) -> Result<Option<Token>, QueryError> {
+        self.skip_whitespace();
+        if self.pos >= self.chars.len() {
+            return Ok(None);
+        }
+        let start = self.pos;
+        let negated = self.chars[self.pos] == '-';
+        if negated {
+            self.pos += 1;
+            if self.pos >= self.chars.len() || self.chars[self.pos].is_whitespace() {
+                return Err(QueryError::new("\"-\" needs a word or filter right after it", start));
+            }
+        }
+        if self.chars[self.pos] == '"' {
+            let phrase = self.read_quoted()?;
+            return Ok(Some(Token::Term(Term { negated, kind: TermKind::Phrase(phrase), alternatives: Vec::new() })));
+        }
+        let bare = self.read_bare();
+        if bare == "OR" && !negated {
+            return Ok(Some(Token::Or));
+        }
+        let field = bare
+            .split_once(':')
+            .and_then(|(field, value)| Field::parse(field).map(|field| (field, value.to_string())));
+        let kind = match field {
+            Some((field, value)) => {
+                let value = if value.is_empty() && self.pos < self.chars.len() && self.chars[self.pos] == '"' {
+                    self.read_quoted()?
+                } else {
+                    value
+                };
+                Self::field_term(field, value.trim(), start)?
+            }
+            // anything else, urls included, is searched as it was typed
+            None => TermKind::Word(bare),
+        };
+        Ok(Some(Token::Term(Term { negated, kind, alternatives: Vec::new() })))
+    }
+    fn field_term(field: Field, value: &str, start: usize) -> Result<TermKind, QueryError> {
+        if value.is_empty() {
+            return Err(QueryError::new(&format!("{}: needs a value", field), start));
+        }
+        match field {
+            Field::Site => Ok(TermKind::Site(value.to_lowercase().trim_start_matches("www.").to_string())),
+            Field::In => {
+                let folders: Vec<String> = value
+                    .split('/')
+                    .map(|folder| folder.trim().to_string())
+                    .filter(|folder| !folder.is_empty())
+                    .collect();
+                if folders.is_empty() {
+                    return Err(QueryError::new("in: needs a folder name", start));
+                }
+                Ok(TermKind::In(folders))
+            }
+            Field::Tag => Ok(TermKind::Tag(value.to_lowercase())),
+            Field::Added => {
+                let (cmp, date) = if let Some(date) = value.strip_prefix(">=") {
+                    (DateCmp::AfterOrOn, date)
+                } else if let Some(date) = value.strip_prefix("<=") {
+                    (DateCmp::BeforeOrOn, date)
+                } else if let Some(date) = value.strip_prefix('>') {
+                    (DateCmp::After, date)
+                } else if let Some(date) = value.strip_prefix('<') {
+                    (DateCmp::Before, date)
+                } else {
+                    (DateCmp::On, value.strip_prefix('=').unwrap_or(value))
+                };
+                if utils::parse_date(date, false).is_none() {
+                    return Err(QueryError::new(
+                        "added: expects a date like 2023-01-01, optionally after >, <, >= or <=",
+                        start,
+                    ));
+                }
+                Ok(TermKind::Added(cmp, date.to_string()))
+            }
+            Field::Status => LinkStatus::from_str(value)
+                .map(TermKind::Status)
+                .map_err(|_| QueryError::new("status: is one of alive, dead or unknown", start)),
+        }
+    }
+}
+
+/// parses the search box, e.g. `rust "borrow checker" OR lifetimes site:github.com -tag:done added:>2023-01-01`
+pub fn parse(input: &str) -> Result<SearchQuery, QueryError> {
+    let mut lexer = Lexer::new(input);
+    let mut clauses: Vec<Vec<Term>> = Vec::new();
+    let mut pending_or: Option<usize> = None;
+    loop {
+        lexer.skip_whitespace();
+        let start = lexer.pos;
+        match lexer.next_token()? {
+            None => break,
+            Some(Token::Or) => {
+                if clauses.is_empty() || pending_or.is_some() {
+                    return Err(QueryError::new("OR needs a search term on both sides", start));
+                }
+                pending_or = Some(start);
+            }
+            Some(Token::Term(term)) => match pending_or.take() {
+                Some(_) => clauses.last_mut().unwrap().push(term),
+                None => clauses.push(vec![term]),
+            },
+        }
+    }
+    if let Some(start) = pending_or {
+        return Err(QueryError::new("OR needs a search term on both sides", start));
+    }
+    // "++" or "(" tokenize to nothing, fts5 can't search for them
+    for clause in clauses.iter_mut() {
+        clause.retain(Term::is_searchable);
+    }
+    clauses.retain(|clause| !clause.is_empty());
+    Ok(SearchQuery { clauses })
+}
+
+// keeps a pathological pattern from eating all the memory while it compiles
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// the regex mode of the search box, case insensitive
+pub fn parse_regex(pattern: &str) -> Result<Regex, QueryError> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|error| QueryError::new(&format!("invalid regex: {}", error), 0))
+}
+
+/// quotes a word or phrase as an fts5 string, so nothing the user types is read as fts5 syntax
+fn fts_string(val: &str) -> String {
+    format!("\"{}\"", val.replace('"', "\"\""))
+}
+
+/// whether the unicode61 tokenizer of the index finds a word in val
+fn has_tokens(val: &str) -> bool {
+    val.chars().any(char::is_alphanumeric)
+}
+
+fn like_escape(val: &str) -> String {
+    val.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+impl Term {
+    /// words and phrases that are only punctuation match nothing, the filters always can
+    fn is_searchable(&self) -> bool {
+        match &self.kind {
+            TermKind::Word(val) | TermKind::Phrase(val) => has_tokens(val),
+            _ => true,
+        }
+    }
+    /// how many typos a word may have and still find what was meant, None when it's searched as typed
+    fn max_typos(&self) -> Option<usize> {
+        match &self.kind {
+            TermKind::Word(word) if !self.negated => match word.chars().count() {
+                len if len < MIN_TYPO_WORD_LEN => None,
+                len if len < LONG_WORD_LEN => Some(1),
+                _ => Some(2),
+            },
+            _ => None,
+        }
+    }
+    /// the fts5 query of a word or phrase, None for the field filters.
+    /// a word also finds the words it starts (rust -> rustup) and its alternatives, negated ones are exact
+    fn fts_query(&self) -> Option<String> {
+        match &self.kind {
+            TermKind::Word(word) if self.negated => Some(fts_string(word)),
+            TermKind::Word(word) => {
+                // the plain word too, the porter stemmer only reads whole words
+                let mut variants = vec![fts_string(word), format!("{}*", fts_string(word))];
+                variants.extend(self.alternatives.iter().map(|alternative| fts_string(alternative)));
+                Some(format!("({})", variants.join(" OR ")))
+            }
+            TermKind::Phrase(phrase) => Some(fts_string(phrase)),
+            _ => None,
+        }
+    }
+    /// an sql condition on the marks table and its parameters
+    fn to_sql(&self, params: &mut Vec<Value>) -> String {
+        let condition = match &self.kind {
+            TermKind::Word(_) | TermKind::Phrase(_) => {
+                params.push(Value::Text(self.fts_query().unwrap_or_default()));
+                String::from("marks.id IN (SELECT id FROM marks_fts WHERE marks_fts MATCH ?)")
+            }
+            TermKind::Site(site) => {
+                params.push(Value::Text(site.clone()));
+                params.push(Value::Text(format!("%.{}", like_escape(site))));
+                String::from("(marks.domain = ? OR marks.domain LIKE ? ESCAPE '\\')")
+            }
+            TermKind::In(folders) => {
+                // the folders have to follow each other somewhere in the path, subfolders included
+                let path = folders.join(CATEGORY_PATH_SEPARATOR);
+                params.push(Value::Text(format!(
+                    "%{sep}{}{sep}%",
+                    like_escape(&path),
+                    sep = CATEGORY_PATH_SEPARATOR
+                )));
+                format!(
+                    "('{sep}' || json_extract(marks.data, '$.category.path') || '{sep}') LIKE ? ESCAPE '\\'",
+                    sep = CATEGORY_PATH_SEPARATOR
+                )
+            }
+            TermKind::Tag(tag) => {
+                params.push(Value::Text(tag.clone()));
+                String::from("EXISTS (SELECT 1 FROM json_each(marks.data, '$.tags') WHERE json_each.value = ?)")
+            }
+            TermKind::Added(cmp, date) => {
+                let start_of_day = utils::parse_date(date, false).unwrap_or_default();
+                let end_of_day = utils::parse_date(date, true).unwrap_or_default();
+                match cmp {
+                    DateCmp::Before => {
+                        params.push(Value::Integer(start_of_day));
+                        String::from("marks.date_added < ?")
+                    }
+                    DateCmp::BeforeOrOn => {
+                        params.push(Value::Integer(end_of_day));
+                        String::from("marks.date_added <= ?")
+                    }
+                    DateCmp::After => {
+                        params.push(Value::Integer(end_of_day));
+                        String::from("marks.date_added > ?")
+                    }
+                    DateCmp::AfterOrOn => {
+                        params.push(Value::Integer(start_of_day));
+                        String::from("marks.date_added >= ?")
+                    }
+                    DateCmp::On => {
+                        params.push(Value::Integer(start_of_day));
+                        params.push(Value::Integer(end_of_day));
+                        String::from("marks.date_added BETWEEN ? AND ?")
+                    }
+                }
+            }
+            TermKind::Status(status) => {
+                params.push(Value::Text(status.to_string()));
+                String::from("COALESCE(json_extract(marks.data, '$.link_status'), 'unknown') = ?")
+            }
+        };
+        if self.negated {
+            // marks without the value (no date, no domain) count as not matching
+            format!("NOT COALESCE({}, 0)", condition)
+        } else {
+            condition
+        }
+    }
+}
+
+impl SearchQuery {
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+    /// fills in the alternatives of every word long enough to be misspelled,
+    /// lookup gets the word and the number of edits allowed
+    pub fn with_alternatives<F>(&self, mut lookup: F) -> SearchQuery
+    where
+        F: FnMut(&str, usize) -> Vec<String>,
+    {
+        let mut search_query = self.clone();
+        for term in search_query.clauses.iter_mut().flatten() {
+            if let (Some(max_typos), TermKind::Word(word)) = (term.max_typos(), &term.kind) {
+                term.alternatives = lookup(word, max_typos);
+            }
+        }
+        search_query
+    }
+    /// the where conditions of the whole query, one per clause
+    pub fn to_sql(&self, params: &mut Vec<Value>) -> Vec<String> {
+        self.clauses
+            .iter()
+            .map(|clause| {
+                let alternatives: Vec<String> = clause.iter().map(|term| term.to_sql(params)).collect();
+                format!("({})", alternatives.join(" OR "))
+            })
+            .collect()
+    }
+    /// the words and phrases the results are ranked and highlighted by, negated ones left out
+    pub fn ranking_query(&self) -> Option<String> {
+        let terms: Vec<String> = self
+            .clauses
+            .iter()
+            .flatten()
+            .filter(|term| !term.negated)
+            .filter_map(|term| term.fts_query())
+            .collect();
+        if terms.is_empty() {
+            None
+        } else {
+            Some(terms.join(" OR "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(kind: TermKind) -> Term {
+        Term { negated: false, kind, alternatives: Vec::new() }
+    }
+
+    fn negated(kind: TermKind) -> Term {
+        Term { negated: true, kind, alternatives: Vec::new() }
+    }
+
+    fn word(val: &str) -> Term {
+        term(TermKind::Word(val.to_string()))
+    }
+
+    /// the only term of a query with one clause of one term
+    fn single(input: &str) -> Term {
+        let mut query = parse(input).unwrap();
+        assert_eq!(query.clauses.len(), 1, "{}", input);
+        assert_eq!(query.clauses[0].len(), 1, "{}", input);
+        query.clauses.remove(0).remove(0)
+    }
+
+    fn error(input: &str) -> QueryError {
+        parse(input).unwrap_err()
+    }
+
+    #[test]
+    fn parses_words_and_phrases() {
+        let query = parse("rust  \"borrow checker\" c++").unwrap();
+        assert_eq!(
+            query.clauses,
+            vec![
+                vec![word("rust")],
+                vec![term(TermKind::Phrase(String::from("borrow checker")))],
+                vec![word("c++")],
+            ]
+        );
+        assert!(parse("   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn parses_the_site_filter() {
+        assert_eq!(single("site:www.GitHub.com").kind, TermKind::Site(String::from("github.com")));
+    }
+
+    #[test]
+    fn parses_the_in_filter() {
+        let folders = |names: &[&str]| TermKind::In(names.iter().map(|name| name.to_string()).collect());
+        assert_eq!(single("in:Rust").kind, folders(&["Rust"]));
+        assert_eq!(single("in:Rust/Async").kind, folders(&["Rust", "Async"]));
+        assert_eq!(single("in:\"a b\"").kind, folders(&["a b"]));
+        assert_eq!(single("in:\"Dev / Rust lang\"").kind, folders(&["Dev", "Rust lang"]));
+    }
+
+    #[test]
+    fn parses_the_tag_filter() {
+        assert_eq!(single("tag:Done").kind, TermKind::Tag(String::from("done")));
+    }
+
+    #[test]
+    fn parses_the_added_filter() {
+        let added = |cmp: DateCmp| TermKind::Added(cmp, String::from("2023-01-01"));
+        assert_eq!(single("added:2023-01-01").kind, added(DateCmp::On));
+        assert_eq!(single("added:=2023-01-01").kind, added(DateCmp::On));
+        assert_eq!(single("added:>2023-01-01").kind, added(DateCmp::After));
+        assert_eq!(single("added:>=2023-01-01").kind, added(DateCmp::AfterOrOn));
+        assert_eq!(single("added:<2023-01-01").kind, added(DateCmp::Before));
+        assert_eq!(single("added:<=2023-01-01").kind, added(DateCmp::BeforeOrOn));
+    }
+
+    #[test]
+    fn parses_the_status_filter() {
+        assert_eq!(single("status:alive").kind, TermKind::Status(LinkStatus::Alive));
+        assert_eq!(single("STATUS:Dead").kind, TermKind::Status(LinkStatus::Dead));
+        assert_eq!(single("status:unknown").kind, TermKind::Status(LinkStatus::Unknown));
+    }
+
+    #[test]
+    fn keeps_unknown_fields_and_urls_as_words() {
+        assert_eq!(single("author:me"), word("author:me"));
+        assert_eq!(single("https://github.com/rust-lang"), word("https://github.com/rust-lang"));
+    }
+
+    #[test]
+    fn parses_negation() {
+        assert_eq!(single("-rust"), negated(TermKind::Word(String::from("rust"))));
+        assert_eq!(single("-\"a b\""), negated(TermKind::Phrase(String::from("a b"))));
+        assert_eq!(single("-tag:done"), negated(TermKind::Tag(String::from("done"))));
+        // a negated OR is the word itself
+        assert_eq!(single("-OR"), negated(TermKind::Word(String::from("OR"))));
+    }
+
+    #[test]
+    fn or_binds_tighter_than_and() {
+        assert_eq!(
+            parse("a b OR c d").unwrap().clauses,
+            vec![vec![word("a")], vec![word("b"), word("c")], vec![word("d")]]
+        );
+        assert_eq!(parse("a OR b OR c").unwrap().clauses, vec![vec![word("a"), word("b"), word("c")]]);
+        // only the upper case OR is the operator
+        assert_eq!(parse("a or b").unwrap().clauses.len(), 3);
+    }
+
+    #[test]
+    fn drops_terms_without_words() {
+        assert_eq!(parse("rust ++ ( \"--\" -* c++").unwrap().clauses, vec![vec![word("rust")], vec![word("c++")]]);
+        assert_eq!(parse("++ OR rust").unwrap().clauses, vec![vec![word("rust")]]);
+        assert!(parse("++ \u{0}").unwrap().is_empty());
+    }
+
+    #[test]
+    fn reports_an_unclosed_quote() {
+        assert_eq!(error("rust \"borrow"), QueryError::new("this quote is never closed", 5));
+        assert_eq!(error("in:\"Rust"), QueryError::new("this quote is never closed", 3));
+    }
+
+    #[test]
+    fn reports_a_dangling_minus() {
+        let message = "\"-\" needs a word or filter right after it";
+        assert_eq!(error("rust -"), QueryError::new(message, 5));
+        assert_eq!(error("- rust"), QueryError::new(message, 0));
+    }
+
+    #[test]
+    fn reports_a_filter_without_value() {
+        assert_eq!(error("site:"), QueryError::new("site: needs a value", 0));
+        assert_eq!(error("rust -tag:"), QueryError::new("tag: needs a value", 5));
+        assert_eq!(error("in:/"), QueryError::new("in: needs a folder name", 0));
+    }
+
+    #[test]
+    fn reports_a_bad_date() {
+        assert_eq!(
+            error("rust added:yesterday"),
+            QueryError::new("added: expects a date like 2023-01-01, optionally after >, <, >= or <=", 5)
+        );
+        assert!(parse("added:>2023-02-30").is_err());
+    }
+
+    #[test]
+    fn reports_a_bad_status() {
+        assert_eq!(error("status:gone"), QueryError::new("status: is one of alive, dead or unknown", 0));
+    }
+
+    #[test]
+    fn reports_an_or_without_both_sides() {
+        let message = "OR needs a search term on both sides";
+        assert_eq!(error("OR rust"), QueryError::new(message, 0));
+        assert_eq!(error("rust OR"), QueryError::new(message, 5));
+        assert_eq!(error("a OR OR b"), QueryError::new(message, 5));
+    }
+
+    #[test]
+    fn shows_the_position_from_one() {
+        assert_eq!(error("rust OR").to_string(), "OR needs a search term on both sides (at character 6)");
+    }
+}
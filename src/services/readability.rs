@@ -0,0 +1,131 @@
+use scraper::{ElementRef, Html, Selector};
+use std::collections::HashMap;
+
+// shorter paragraphs are captions, bylines and buttons
+const MIN_PARAGRAPH_CHARS: usize = 25;
+// less than this and the page is not an article, the plain page text is kept instead
+const MIN_ARTICLE_CHARS: usize = 250;
+const MAX_ARTICLE_CHARS: usize = 200_000;
+// class and id parts of the page chrome and of the article itself
+const UNLIKELY_NAMES: [&str; 17] = [
+    "nav", "footer", "sidebar", "comment", "share", "social", "promo", "advert", "banner", "cookie", "related",
+    "subscribe", "newsletter", "menu", "breadcrumb", "popup", "modal",
+];
+const LIKELY_NAMES: [&str; 9] = ["article", "content", "post", "entry", "main", "story", "text", "body", "prose"];
+const SKIPPED_TAGS: [&str; 13] = [
+    "script", "style", "noscript", "template", "nav", "aside", "footer", "header", "form", "button", "iframe", "svg",
+    "select",
+];
+const BLOCKS: &str = "h1, h2, h3, h4, h5, h6, p, pre, li, blockquote";
+
+fn normalized_text(element: ElementRef) -> String {
+    element.text().collect::<Vec<&str>>().join(" ").split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// a bonus for elements named like an article, a penalty for the ones named like page chrome
+fn class_weight(element: ElementRef) -> f32 {
+    let names = format!(
+        "{} {}",
+        element.value().attr("class").unwrap_or_default(),
+        element.value().attr("id").unwrap_or_default()
+    )
+    .to_lowercase();
+    let mut weight = 0.0;
+    if UNLIKELY_NAMES.iter().any(|name| names.contains(name)) {
+        weight -= 25.0;
+    }
+    if LIKELY_NAMES.iter().any(|name| names.contains(name)) {
+        weight += 25.0;
+    }
+    weight
+}
+
+fn is_skipped(element: ElementRef) -> bool {
+    SKIPPED_TAGS.contains(&element.value().name()) || class_weight(element) < 0.0
+}
+
+/// the share of the text that is link text, menus and link lists are mostly links
+fn link_density(element: ElementRef, links: &Selector) -> f32 {
+    let text_len = normalized_text(element).len();
+    if text_len == 0 {
+        return 1.0;
+    }
+    let link_len: usize = element.select(links).map(|link| normalized_text(link).len()).sum();
+    (link_len as f32 / text_len as f32).min(1.0)
+}
+
+/// the article text as markdown like blocks: "#" headings, "-" list items, ">" quotes, one blank line apart
+fn collect_blocks(element: ElementRef, blocks_selector: &Selector, blocks: &mut Vec<String>) {
+    for child in element.children().filter_map(ElementRef::wrap) {
+        if is_skipped(child) {
+            continue;
+        }
+        let name = child.value().name();
+        let text = normalized_text(child);
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" if !text.is_empty() => {
+                let level = name[1..].parse::<usize>().unwrap_or(1);
+                blocks.push(format!("{} {}", "#".repeat(level), text));
+            }
+            "p" if !text.is_empty() => blocks.push(text),
+            "pre" => {
+                let code = child.text().collect::<String>();
+                if !code.trim().is_empty() {
+                    blocks.push(format!("```\n{}\n```", code.trim_end()));
+                }
+            }
+            "li" if !text.is_empty() => blocks.push(format!("- {}", text)),
+            "blockquote" if !text.is_empty() => blocks.push(format!("> {}", text)),
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "p" | "li" | "blockquote" => (),
+            // text sitting right in a div is a paragraph too
+            _ if child.select(blocks_selector).next().is_none() => {
+                if text.len() >= MIN_PARAGRAPH_CHARS {
+                    blocks.push(text);
+                }
+            }
+            _ => collect_blocks(child, blocks_selector, blocks),
+        }
+    }
+}
+
+/// finds the main article of a page the way readability does: every paragraph scores its parent
+/// (and half for the grandparent) by its length and commas, the best scored element with few links wins
+pub fn extract_article(html: &Html) -> Option<String> {
+    let paragraphs = Selector::parse("p, pre").ok()?;
+    let links = Selector::parse("a").ok()?;
+    let blocks_selector = Selector::parse(BLOCKS).ok()?;
+    let mut scores = HashMap::new();
+    for paragraph in html.select(&paragraphs) {
+        let hidden = paragraph.ancestors().filter_map(ElementRef::wrap).any(is_skipped);
+        let text = normalized_text(paragraph);
+        if hidden || text.len() < MIN_PARAGRAPH_CHARS {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f32 + (text.len() as f32 / 100.0).min(3.0);
+        let parent = paragraph.parent().and_then(ElementRef::wrap);
+        if let Some(parent) = parent {
+            *scores.entry(parent.id()).or_insert_with(|| class_weight(parent)) += score;
+            if let Some(grandparent) = parent.parent().and_then(ElementRef::wrap) {
+                *scores.entry(grandparent.id()).or_insert_with(|| class_weight(grandparent)) += score / 2.0;
+            }
+        }
+    }
+    let (best, _) = scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let element = ElementRef::wrap(html.tree.get(id)?)?;
+            Some((element, score * (1.0 - link_density(element, &links))))
+        })
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))?;
+    let mut blocks: Vec<String> = Vec::new();
+    collect_blocks(best, &blocks_selector, &mut blocks);
+    let mut article = blocks.join("\n\n");
+    if article.len() < MIN_ARTICLE_CHARS {
+        return None;
+    }
+    if article.len() > MAX_ARTICLE_CHARS {
+        let end = (0..=MAX_ARTICLE_CHARS).rev().find(|i| article.is_char_boundary(*i)).unwrap_or(0);
+        article.truncate(end);
+    }
+    Some(article)
+}
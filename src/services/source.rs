@@ -0,0 +1,225 @@
+use crate::config;
+use crate::services::bookmark_api::BookmarkFile;
+use crate::services::chromium::{ChromiumBrowser, ChromiumSource};
+use crate::services::firefox::FirefoxPlaces;
+use crate::services::netscape::NetscapeSource;
+use crate::user_config;
+use crate::utils::Error;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct SourceInfo {
+    pub id: String,
+    pub browser: String,
+    pub profile: String,
+}
+
+impl SourceInfo {
+    /// the id is built from the profile directory which stays stable when the profile gets renamed
+    pub fn new(browser_key: &str, browser: &str, profile_key: &str, profile_name: &str) -> Self {
+        SourceInfo {
+            id: format!("{}-{}", browser_key, Self::slug(profile_key)),
+            browser: browser.to_string(),
+            profile: profile_name.to_string(),
+        }
+    }
+    /// source ids end up in bookmark ids and image file names, so they are kept to [a-z0-9-]
+    pub fn slug(val: &str) -> String {
+        val.to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect()
+    }
+    /// e.g. "chrome", "firefox" or "html", the browser keys have no dash
+    pub fn browser_key(&self) -> &str {
+        self.id.split('-').next().unwrap_or_default()
+    }
+    /// the slug of the profile directory, e.g. "profile-1"
+    pub fn profile_key(&self) -> &str {
+        self.id.splitn(2, '-').nth(1).unwrap_or_default()
+    }
+    /// whether the source is the browser and profile asked for, by key or by the name shown
+    pub fn is_wanted(&self, browser: &Option<String>, profile: &Option<String>) -> bool {
+        let browser_matches = browser.as_ref().map_or(true, |browser| {
+            browser.eq_ignore_ascii_case(self.browser_key()) || browser.eq_ignore_ascii_case(&self.browser)
+        });
+        let profile_matches = profile.as_ref().map_or(true, |profile| {
+            Self::slug(profile) == self.profile_key() || profile.eq_ignore_ascii_case(&self.profile)
+        });
+        browser_matches && profile_matches
+    }
+}
+
+impl fmt::Display for SourceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.browser, self.profile)
+    }
+}
+
+/// why the bookmarks of a source couldn't be read, shown next to the source
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceError {
+    Missing(PathBuf),
+    /// the file is there but isn't a bookmarks file the source understands
+    Corrupt(String),
+    /// a root folder the browser always writes isn't in the file
+    MissingRoot(String),
+    Unreadable(String),
+}
+
+impl SourceError {
+    pub fn from_io(path: &Path, error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::NotFound => SourceError::Missing(path.to_path_buf()),
+            _ => SourceError::Unreadable(format!("{}: {}", path.display(), error)),
+        }
+    }
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceError::Missing(path) => write!(f, "the bookmarks file {} is missing", path.display()),
+            SourceError::Corrupt(reason) => write!(f, "the bookmarks file is corrupt: {}", reason),
+            SourceError::MissingRoot(root) => write!(f, "the bookmarks file has no \"{}\" folder", root),
+            SourceError::Unreadable(reason) => write!(f, "the bookmarks file can't be read: {}", reason),
+        }
+    }
+}
+
+impl From<Error> for SourceError {
+    fn from(error: Error) -> SourceError {
+        SourceError::Unreadable(error.to_string())
+    }
+}
+
+pub trait BookmarkSource: Send {
+    fn info(&self) -> SourceInfo;
+    fn read_bookmark_file(&self) -> Result<BookmarkFile, SourceError>;
+    /// the files whose changes mean the bookmarks changed
+    fn watch_paths(&self) -> Vec<PathBuf>;
+}
+
+/// finds every browser profile with a bookmarks file on this machine
+pub fn discover_sources() -> Vec<Box<dyn BookmarkSource>> {
+    let mut sources: Vec<Box<dyn BookmarkSource>> = ChromiumBrowser::all()
+        .into_iter()
+        .flat_map(|browser| ChromiumSource::discover(browser))
+        .map(|source| Box::new(source) as Box<dyn BookmarkSource>)
+        .collect();
+    if let Some(places_path) = config::get_firefox_places_path() {
+        sources.push(Box::new(FirefoxPlaces::new(places_path)));
+    }
+    if let Ok(entries) = fs::read_dir(config::get_imports_dir()) {
+        let mut import_paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file())
+            .collect();
+        import_paths.sort();
+        sources.extend(
+            import_paths
+                .into_iter()
+                .map(|path| Box::new(NetscapeSource::new(path)) as Box<dyn BookmarkSource>),
+        );
+    }
+    let user_config = user_config::get();
+    if let Some(path) = &user_config.bookmarks_file {
+        sources.push(Box::new(NetscapeSource::new(path.clone())));
+    }
+    sources.extend(load_source_settings().files.into_iter().map(picked_file_source));
+    // the browser and profile of the config narrow the browser profiles, imported files stay
+    sources
+        .into_iter()
+        .filter(|source| {
+            let info = source.info();
+            info.browser == NetscapeSource::BROWSER || info.is_wanted(&user_config.browser, &user_config.profile)
+        })
+        .collect()
+}
+
+/// html files are read as netscape exports, anything else as a chromium Bookmarks file
+pub fn picked_file_source(path: PathBuf) -> Box<dyn BookmarkSource> {
+    let is_html = path
+        .extension()
+        .map_or(false, |extension| extension.eq_ignore_ascii_case("html") || extension.eq_ignore_ascii_case("htm"));
+    if is_html {
+        Box::new(NetscapeSource::new(path))
+    } else {
+        Box::new(ChromiumSource::from_file(path))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SourceSettings {
+    /// ids of the sources the user turned on, None means nothing was chosen yet and every source is used
+    pub enabled: Option<Vec<String>>,
+    /// the profile picked in the header, None shows the merged library of the enabled sources
+    pub active: Option<String>,
+    /// the first-run screen was closed
+    #[serde(default)]
+    pub onboarded: bool,
+    /// bookmarks files picked by hand, for browsers installed in a place that isn't searched
+    #[serde(default)]
+    pub files: Vec<PathBuf>,
+}
+
+pub fn load_source_settings() -> SourceSettings {
+    fs::read_to_string(config::get_sources_file_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_source_settings(settings: &SourceSettings) -> Result<(), Error> {
+    let content = serde_json::to_string_pretty(settings).unwrap_or_default();
+    fs::write(config::get_sources_file_path(), content)?;
+    Ok(())
+}
+
+pub fn is_enabled(enabled: &Option<Vec<String>>, info: &SourceInfo) -> bool {
+    match enabled {
+        Some(ids) => ids.contains(&info.id),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wanted(browser: Option<&str>, profile: Option<&str>) -> (Option<String>, Option<String>) {
+        (browser.map(String::from), profile.map(String::from))
+    }
+
+    #[test]
+    fn picks_the_browser_by_key_or_name() {
+        let chrome = SourceInfo::new(ChromiumBrowser::Chrome.key(), ChromiumBrowser::Chrome.name(), "Profile 1", "Work");
+        let firefox = SourceInfo::new("firefox", "Firefox", "abcd.default-release", "abcd.default-release");
+        for browser in ["chrome", "Google Chrome", "CHROME"] {
+            let (browser, profile) = wanted(Some(browser), None);
+            assert!(chrome.is_wanted(&browser, &profile), "{:?}", browser);
+            assert!(!firefox.is_wanted(&browser, &profile), "{:?}", browser);
+        }
+        let (browser, profile) = wanted(Some("firefox"), None);
+        assert!(firefox.is_wanted(&browser, &profile));
+        let (browser, profile) = wanted(Some("Chromium"), None);
+        assert!(!chrome.is_wanted(&browser, &profile));
+    }
+
+    #[test]
+    fn picks_the_profile_by_directory_or_name() {
+        let chrome = SourceInfo::new(ChromiumBrowser::Chrome.key(), ChromiumBrowser::Chrome.name(), "Profile 1", "Work");
+        for profile in ["Profile 1", "profile-1", "Work", "work"] {
+            let (browser, profile) = wanted(Some("chrome"), Some(profile));
+            assert!(chrome.is_wanted(&browser, &profile), "{:?}", profile);
+        }
+        let (browser, profile) = wanted(None, Some("Default"));
+        assert!(!chrome.is_wanted(&browser, &profile));
+        let (browser, profile) = wanted(None, None);
+        assert!(chrome.is_wanted(&browser, &profile));
+    }
+}
@@ -0,0 +1,210 @@
+use crate::config;
+use crate::services::bookmark_api::{BookmarkCategory, BookmarksItem, MarkData};
+use crate::utils::{self, Error};
+use futures::channel::mpsc::UnboundedSender;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncState {
+    /// the checksum of the bookmarks file of every source at its last complete sync
+    pub checksums: HashMap<String, String>,
+}
+
+pub fn load_sync_state() -> SyncState {
+    fs::read_to_string(config::get_sync_state_file_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_sync_state(state: &SyncState) -> Result<(), Error> {
+    let content = serde_json::to_string_pretty(state).unwrap_or_default();
+    fs::write(config::get_sync_state_file_path(), content)?;
+    Ok(())
+}
+
+/// what changed in a source since the marks were cached
+#[derive(Debug, Default)]
+pub struct BookmarkDiff {
+    /// bookmarks that have no cached mark yet
+    pub added: Vec<(BookmarksItem, BookmarkCategory)>,
+    /// cached marks whose bookmark is gone from the browser
+    pub removed: Vec<(String, MarkData)>,
+    /// bookmarks pointing to another url, the old mark is kept until the new page is scraped
+    pub url_changed: Vec<(BookmarksItem, BookmarkCategory, MarkData)>,
+    pub renamed: Vec<String>,
+    pub moved: Vec<String>,
+    /// the cached marks with the new title, folder and dates applied, nothing to scrape
+    pub updated: Vec<(String, MarkData)>,
+}
+
+impl BookmarkDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.url_changed.is_empty() && self.updated.is_empty()
+    }
+}
+
+/// compares the bookmarks of one source with the marks cached for it
+pub fn diff(current: Vec<(BookmarksItem, BookmarkCategory)>, mut cached: BTreeMap<String, MarkData>) -> BookmarkDiff {
+    let mut result = BookmarkDiff::default();
+    for (item, category) in current.into_iter() {
+        let mut mark = match cached.remove(&item.id) {
+            Some(mark) => mark,
+            None => {
+                result.added.push((item, category));
+                continue;
+            }
+        };
+        if item.url.as_ref() != Some(&mark.link) {
+            result.url_changed.push((item, category, mark));
+            continue;
+        }
+        let mut changed = false;
+        // a bookmark without a name keeps the title of its page
+        if !item.name.trim().is_empty() && mark.title != item.name {
+            mark.title = item.name.clone();
+            result.renamed.push(item.id.clone());
+            changed = true;
+        }
+        if mark.category.id != category.id || mark.category.path != category.path {
+            mark.category = category;
+            result.moved.push(item.id.clone());
+            changed = true;
+        }
+        if mark.tags != item.tags {
+            mark.tags = item.tags.clone();
+            changed = true;
+        }
+        let date_added = utils::chrome_time_to_unix(&item.date_added);
+        let date_last_used = item.date_last_used.as_ref().and_then(|date| utils::chrome_time_to_unix(date));
+        if mark.date_added != date_added || mark.date_last_used != date_last_used {
+            mark.date_added = date_added;
+            mark.date_last_used = date_last_used;
+            changed = true;
+        }
+        if changed {
+            result.updated.push((item.id, mark));
+        }
+    }
+    result.removed = cached.into_iter().collect();
+    result
+}
+
+/// the part of the sync that is running, each one counts its own items
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyncStage {
+    Scraping,
+    Archiving,
+    CheckingLinks,
+}
+
+impl std::fmt::Display for SyncStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncStage::Scraping => write!(f, "scraping"),
+            SyncStage::Archiving => write!(f, "archiving"),
+            SyncStage::CheckingLinks => write!(f, "checking links"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncProgress {
+    pub stage: SyncStage,
+    pub total: usize,
+    /// failed ones included
+    pub done: usize,
+    pub failed: usize,
+    /// the last url that was started
+    pub current_url: Option<String>,
+    pub started: Instant,
+}
+
+impl SyncProgress {
+    pub fn new(stage: SyncStage, total: usize) -> Self {
+        SyncProgress {
+            stage,
+            total,
+            done: 0,
+            failed: 0,
+            current_url: None,
+            started: Instant::now(),
+        }
+    }
+    /// how long the rest of the stage takes at the pace so far, None until something is done
+    pub fn eta(&self) -> Option<Duration> {
+        if self.done == 0 || self.done >= self.total {
+            return None;
+        }
+        let per_item = self.started.elapsed() / self.done as u32;
+        Some(per_item * (self.total - self.done) as u32)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum SyncEvent {
+    Progress(SyncProgress),
+    /// a mark was scraped and saved, it can be shown before the sync is done.
+    /// true when the bookmark wasn't cached before
+    MarkSaved(MarkData, bool),
+    /// always the last event, with everything that went wrong
+    Finished(Vec<Error>),
+}
+
+/// sends the progress of a running sync, nothing is sent once nobody listens anymore
+pub struct SyncReporter {
+    sender: UnboundedSender<SyncEvent>,
+    progress: Mutex<Option<SyncProgress>>,
+}
+
+impl SyncReporter {
+    pub fn new(sender: UnboundedSender<SyncEvent>) -> Self {
+        SyncReporter {
+            sender,
+            progress: Mutex::new(None),
+        }
+    }
+    fn send(&self, event: SyncEvent) {
+        self.sender.unbounded_send(event).ok();
+    }
+    fn update(&self, change: impl FnOnce(&mut SyncProgress)) {
+        let progress = match self.progress.lock() {
+            Ok(mut progress) => progress.as_mut().map(|progress| {
+                change(progress);
+                progress.clone()
+            }),
+            Err(_) => None,
+        };
+        if let Some(progress) = progress {
+            self.send(SyncEvent::Progress(progress));
+        }
+    }
+    /// starts counting a new stage from zero
+    pub fn stage(&self, stage: SyncStage, total: usize) {
+        if let Ok(mut progress) = self.progress.lock() {
+            *progress = Some(SyncProgress::new(stage, total));
+        }
+        self.update(|_| ());
+    }
+    pub fn started(&self, url: &str) {
+        self.update(|progress| progress.current_url = Some(url.to_string()));
+    }
+    pub fn finished(&self, ok: bool) {
+        self.update(|progress| {
+            progress.done += 1;
+            if !ok {
+                progress.failed += 1;
+            }
+        });
+    }
+    pub fn mark_saved(&self, mark: &MarkData, new: bool) {
+        self.send(SyncEvent::MarkSaved(mark.clone(), new));
+    }
+    pub fn finish(&self, errors: Vec<Error>) {
+        self.send(SyncEvent::Finished(errors));
+    }
+}
@@ -1,4 +1,5 @@
-use iced::{container, Background, Color, button, Vector, text_input};
+use iced::{container, Background, Color, button, Vector, text_input, progress_bar};
+use crate::user_config::{self, Theme};
 
 pub const PRIMARY_COLOR: Color = Color::from_rgba(34 as f32 / 255.0, 40 as f32 / 255.0, 49 as f32 / 255.0, 1.0);
 pub const LIGHT_PRIMARY_COLOR: Color = Color::from_rgba(57 as f32 / 255.0, 62 as f32 / 255.0, 70 as f32 / 255.0, 0.8);
@@ -30,13 +31,47 @@ pub const HOVERED: Color = Color::from_rgb(
     0x7B as f32 / 255.0,
     0xC4 as f32 / 255.0,
 );
+const LIGHT_SURFACE_COLOR: Color = Color::from_rgba(245 as f32 / 255.0, 245 as f32 / 255.0, 245 as f32 / 255.0, 1.0);
+const LIGHT_PANEL_COLOR: Color = Color::from_rgba(255 as f32 / 255.0, 255 as f32 / 255.0, 255 as f32 / 255.0, 0.9);
+const LIGHT_THEME_SECONDARY_COLOR: Color = Color::from_rgba(140 as f32 / 255.0, 100 as f32 / 255.0, 0 as f32 / 255.0, 1.0);
+
+/// the window background
+pub fn surface_color() -> Color {
+    match user_config::get().theme {
+        Theme::Dark => PRIMARY_COLOR,
+        Theme::Light => LIGHT_SURFACE_COLOR,
+    }
+}
+
+/// the background of the header, cards, inputs and plain buttons
+pub fn panel_color() -> Color {
+    match user_config::get().theme {
+        Theme::Dark => LIGHT_PRIMARY_COLOR,
+        Theme::Light => LIGHT_PANEL_COLOR,
+    }
+}
+
+pub fn text_color() -> Color {
+    match user_config::get().theme {
+        Theme::Dark => TEXT_COLOR,
+        Theme::Light => PRIMARY_COLOR,
+    }
+}
+
+pub fn secondary_color() -> Color {
+    match user_config::get().theme {
+        Theme::Dark => SECONDARY_COLOR,
+        Theme::Light => LIGHT_THEME_SECONDARY_COLOR,
+    }
+}
+
 pub struct FeedCard;
 
 impl container::StyleSheet for FeedCard {
     fn style(&self) -> container::Style {
         container::Style {
-            background: Some(Background::Color(LIGHT_PRIMARY_COLOR)),
-            text_color: Some(TEXT_COLOR),
+            background: Some(Background::Color(panel_color())),
+            text_color: Some(text_color()),
             border_color: BORDER_COLOR,
             border_width: 1.0,
             border_radius: 10.0,
@@ -50,7 +85,7 @@ pub struct Surface;
 impl container::StyleSheet for Surface {
     fn style(&self) -> container::Style {
         container::Style {
-            background: Some(Background::Color(PRIMARY_COLOR)),
+            background: Some(Background::Color(surface_color())),
             ..container::Style::default()
         }
     }
@@ -136,7 +171,7 @@ impl StyledButton{
         Self::new(ButtonStylingType::Specific(
             ButtonStyle{
                 background_color: ACCENT,
-                text_color: TEXT_COLOR,
+                text_color: text_color(),
                 border_radius: 0.0
             }
         ))
@@ -144,8 +179,8 @@ impl StyledButton{
     pub fn primary() -> Self{
         Self::new(ButtonStylingType::Specific(
             ButtonStyle{
-                background_color: LIGHT_PRIMARY_COLOR,
-                text_color: TEXT_COLOR,
+                background_color: panel_color(),
+                text_color: text_color(),
                 border_radius: 1.0
             }
         ))
@@ -207,7 +242,7 @@ pub struct Header;
 impl container::StyleSheet for Header{
     fn style(&self) -> container::Style {
         container::Style{
-            background: Some(Background::Color(LIGHT_PRIMARY_COLOR)),
+            background: Some(Background::Color(panel_color())),
             border_radius: 1.0,
             border_width: 1.0,
             border_color: BORDER_COLOR,
@@ -215,6 +250,17 @@ impl container::StyleSheet for Header{
         }
     }
 }
+pub struct SyncProgressBar;
+
+impl progress_bar::StyleSheet for SyncProgressBar {
+    fn style(&self) -> progress_bar::Style {
+        progress_bar::Style {
+            background: Background::Color(surface_color()),
+            bar: Background::Color(ACCENT),
+            border_radius: 5.0,
+        }
+    }
+}
 struct TextInputStyle{
     background_color: Color,
     text_color: Color,
@@ -230,8 +276,8 @@ impl StyledTextInput{
     pub fn new() -> Self{
         Self{
             style: TextInputStyle{
-                background_color: LIGHT_PRIMARY_COLOR,
-                text_color: TEXT_COLOR,
+                background_color: panel_color(),
+                text_color: text_color(),
                 border_color: Color::TRANSPARENT,
                 selection_color: Color::WHITE,
                 border_width: 0.0,
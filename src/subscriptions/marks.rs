@@ -3,11 +3,10 @@ use futures::{Future, StreamExt};
 use iced::Subscription;
 use iced_native::subscription;
 use tokio::{self, time};
-use crate::services::bookmark_api::{BookmarkApi, BookmarkCategory, MarkMeta};
+use crate::services::bookmark_api::{BookmarkApi, MarkMeta, MarksPage, MarksQuery};
 
 pub struct MarksRecipe{
-    category: Option<BookmarkCategory>,
-    search: Option<String>,
+    query: MarksQuery,
     last_index: usize
 
 }
@@ -24,30 +23,43 @@ where H: Hasher,
         _input: futures::stream::BoxStream<'static, I>,
     ) -> futures::stream::BoxStream<'static, Self::Output>
      {
-         let (has_more, marks) = BookmarkApi::load_marks_sync(self.last_index,self.category, self.search).unwrap();
-        let marks_length: usize = marks.len().clone();
+        // a query that fails shows the empty state and reports why
+        let (page, error) = match BookmarkApi::load_marks_sync(self.query.clone()) {
+            Ok(page) => (page, None),
+            Err(error) => (MarksPage::default(), Some(error)),
+        };
+        let query = self.query;
+        let total = page.total;
+        let has_more = page.next.is_some();
+        let next = page.next;
         let last_index = self.last_index.clone();
-        if marks.len() > 0 {
-            let stream = futures::stream::iter(marks.into_iter());
+        if page.marks.len() > 0 {
+            let stream = futures::stream::iter(page.marks.into_iter());
             let stream = stream.enumerate();
-            stream.then(move |(i, item)| async move {
+            stream.then(move |(i, item)| {
+                let query = query.clone();
+                let next = next.clone();
+                async move {
                     MarkMeta{
-                    mark: Some(item.clone()),
-                    index: i + last_index,
-                    all_len: last_index + marks_length,
-                    has_more
+                        mark: Some(item.clone()),
+                        index: i + last_index,
+                        all_len: total,
+                        has_more,
+                        next,
+                        query,
+                        error: None
+                    }
                 }
             }).boxed()
         } else {
-            futures::stream::iter(0..1).map(|_| MarkMeta{mark: None, index: 0, all_len: 0, has_more: false}).boxed()
+            futures::stream::iter(0..1).map(move |_| MarkMeta{mark: None, index: 0, all_len: 0, has_more: false, next: None, query: query.clone(), error: error.clone()}).boxed()
         }
     }
 }
 
-pub fn load_marks(last_index: &usize, cat: Option<BookmarkCategory>, search: Option<String>) -> Subscription<MarkMeta> {
+pub fn load_marks(last_index: &usize, query: MarksQuery) -> Subscription<MarkMeta> {
     Subscription::from_recipe(MarksRecipe{
-        category: cat,
-        search,
+        query,
         last_index: *last_index
     })
 }
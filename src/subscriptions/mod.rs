@@ -1 +1,4 @@
-pub mod marks;
\ No newline at end of file
+pub mod marks;
+pub mod scroll;
+pub mod watcher;
+pub mod sync;
\ No newline at end of file
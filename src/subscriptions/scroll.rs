@@ -0,0 +1,25 @@
+use iced::{keyboard, Event, Subscription};
+use iced_native::{event, subscription};
+
+/// the keys that load the next page of marks, the scrolling itself is reported by ScrollWatch
+#[derive(Debug, Clone, Copy)]
+pub enum ScrollEvent {
+    /// End or Page Down while no text input has the focus
+    LoadMoreKey,
+}
+
+fn to_scroll_event(event: Event, status: event::Status) -> Option<ScrollEvent> {
+    match event {
+        Event::Keyboard(keyboard::Event::KeyPressed { key_code: keyboard::KeyCode::End, .. })
+        | Event::Keyboard(keyboard::Event::KeyPressed { key_code: keyboard::KeyCode::PageDown, .. })
+            if status == event::Status::Ignored =>
+        {
+            Some(ScrollEvent::LoadMoreKey)
+        }
+        _ => None,
+    }
+}
+
+pub fn scroll_events() -> Subscription<ScrollEvent> {
+    subscription::events_with(to_scroll_event)
+}
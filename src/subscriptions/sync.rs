@@ -0,0 +1,42 @@
+use std::hash::{Hasher, Hash};
+use futures::{channel::mpsc as async_mpsc, StreamExt};
+use iced::Subscription;
+use iced_native::subscription;
+use crate::services::bookmark_api::BookmarkApi;
+use crate::services::sync::{SyncEvent, SyncReporter};
+
+pub struct SyncRecipe {
+    // every sync gets its own stream, the same number keeps the running one
+    run: u64,
+}
+
+impl<H, I> subscription::Recipe<H, I> for SyncRecipe
+where H: Hasher,
+{
+    type Output = SyncEvent;
+    fn hash(&self, state: &mut H) {
+        std::any::TypeId::of::<Self>().hash(state);
+        self.run.hash(state);
+    }
+    fn stream(
+        self: Box<Self>,
+        _input: futures::stream::BoxStream<'static, I>,
+    ) -> futures::stream::BoxStream<'static, Self::Output>
+    {
+        let (sender, receiver) = async_mpsc::unbounded();
+        // the sync runs as part of the stream and yields nothing itself, so Finished
+        // comes through the channel after every other event
+        let sync = futures::stream::once(async move {
+            let reporter = SyncReporter::new(sender);
+            let errors = BookmarkApi::sync_all(&reporter).await;
+            reporter.finish(errors);
+        })
+        .filter_map(|_| futures::future::ready(None));
+        futures::stream::select(receiver, sync).boxed()
+    }
+}
+
+/// syncs the library and streams its progress, the events end with SyncEvent::Finished
+pub fn sync_library(run: u64) -> Subscription<SyncEvent> {
+    Subscription::from_recipe(SyncRecipe { run })
+}
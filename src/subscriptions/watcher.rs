@@ -0,0 +1,90 @@
+use std::{hash::{Hasher, Hash}, path::PathBuf, sync::mpsc, thread, time::Duration};
+use futures::{channel::mpsc as async_mpsc, StreamExt};
+use iced::Subscription;
+use iced_native::subscription;
+use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
+
+// chrome saves a couple of times in a row when bookmarks are dragged around
+const DEBOUNCE_DELAY: Duration = Duration::from_secs(2);
+// how often the watcher thread checks if the subscription is gone
+const POLL_DELAY: Duration = Duration::from_secs(1);
+
+pub struct WatchRecipe {
+    paths: Vec<PathBuf>,
+}
+
+impl WatchRecipe {
+    /// chrome writes "Bookmarks.tmp" and renames it over "Bookmarks", so the parent
+    /// dirs are watched and the events are matched on the final path
+    fn is_watched(paths: &Vec<PathBuf>, event: &DebouncedEvent) -> bool {
+        match event {
+            DebouncedEvent::Create(path)
+            | DebouncedEvent::Write(path)
+            | DebouncedEvent::Chmod(path)
+            | DebouncedEvent::Rename(_, path) => paths.contains(path),
+            DebouncedEvent::Rescan => true,
+            _ => false,
+        }
+    }
+    fn watch(paths: Vec<PathBuf>, sender: async_mpsc::UnboundedSender<()>) {
+        let (tx, rx) = mpsc::channel();
+        let mut dir_watcher = match watcher(tx, DEBOUNCE_DELAY) {
+            Ok(dir_watcher) => dir_watcher,
+            Err(_) => return,
+        };
+        let mut dirs: Vec<PathBuf> = paths.iter().filter_map(|path| path.parent().map(|dir| dir.to_path_buf())).collect();
+        dirs.sort();
+        dirs.dedup();
+        for dir in dirs.iter() {
+            dir_watcher.watch(dir, RecursiveMode::NonRecursive).ok();
+        }
+        loop {
+            match rx.recv_timeout(POLL_DELAY) {
+                Ok(event) => {
+                    // everything that piled up while the sync was starting counts as one change
+                    let mut changed = Self::is_watched(&paths, &event);
+                    while let Ok(event) = rx.try_recv() {
+                        changed = changed || Self::is_watched(&paths, &event);
+                    }
+                    if changed && sender.unbounded_send(()).is_err() {
+                        return;
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    if sender.is_closed() {
+                        return;
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+}
+
+impl<H, I> subscription::Recipe<H, I> for WatchRecipe
+where H: Hasher,
+{
+    type Output = ();
+    fn hash(&self, state: &mut H) {
+        std::any::TypeId::of::<Self>().hash(state);
+        self.paths.hash(state);
+    }
+    fn stream(
+        self: Box<Self>,
+        _input: futures::stream::BoxStream<'static, I>,
+    ) -> futures::stream::BoxStream<'static, Self::Output>
+    {
+        let (sender, receiver) = async_mpsc::unbounded();
+        let paths = self.paths;
+        thread::spawn(move || Self::watch(paths, sender));
+        receiver.boxed()
+    }
+}
+
+/// emits once the bookmark files of the library settle after a change
+pub fn watch_bookmarks(paths: Vec<PathBuf>) -> Subscription<()> {
+    if paths.is_empty() {
+        return Subscription::none();
+    }
+    Subscription::from_recipe(WatchRecipe { paths })
+}
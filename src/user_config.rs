@@ -0,0 +1,381 @@
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+pub const CONFIG_FILE_PATH: &str = "robozaPress/config.toml";
+const ENV_PREFIX: &str = "ROBOZA_";
+const MAX_PAGE_SIZE: usize = 500;
+const MAX_COLUMNS: usize = 4;
+const MIN_WINDOW_WIDTH: u32 = 800;
+const MIN_WINDOW_HEIGHT: u32 = 600;
+
+static USER_CONFIG: OnceCell<UserConfig> = OnceCell::new();
+
+pub const USAGE: &str = "usage: RobozaPress [options]
+
+options override the config file, ROBOZA_<OPTION> environment variables override it too
+(e.g. ROBOZA_PAGE_SIZE=24)
+
+  --config <path>               the config file, defaults to <config dir>/robozaPress/config.toml
+  --bookmarks-file <path>       an extra bookmarks html file to read
+  --browser <name>              only read the profiles of this browser
+  --profile <name>              only read this profile
+  --cache-dir <path>            where the robozaPress cache is kept
+  --page-size <n>               marks per page
+  --columns <n>                 cards per row
+  --scrape-concurrency <n>      pages scraped at the same time
+  --scrape-timeout <secs>       how long a page may take to scrape
+  --link-check-timeout <secs>   how long a link may take to answer the health check
+  --archive-timeout <secs>      how long a page may take to archive
+  --window-width <px>
+  --window-height <px>
+  --theme <dark|light>
+  --verbose                     log debug events and print every event to the terminal
+  --help                        print this and exit";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    Dark,
+    Light,
+}
+
+impl FromStr for Theme {
+    type Err = String;
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        match val.trim().to_lowercase().as_str() {
+            "dark" => Ok(Theme::Dark),
+            "light" => Ok(Theme::Light),
+            _ => Err(format!("unknown theme \"{}\", use dark or light", val)),
+        }
+    }
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Theme::Dark => write!(f, "dark"),
+            Theme::Light => write!(f, "light"),
+        }
+    }
+}
+
+/// the settings read from config.toml, every key is optional
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserConfig {
+    /// an extra netscape html bookmarks file, read like an imported one
+    pub bookmarks_file: Option<PathBuf>,
+    /// only the profiles of this browser are read, its key or its name, e.g. "chrome" or "Google Chrome"
+    pub browser: Option<String>,
+    /// only this profile is read, its directory or its name, e.g. "Profile 1" or "Work"
+    pub profile: Option<String>,
+    /// replaces the platform cache dir, the robozaPress folder is made inside it
+    pub cache_dir: Option<PathBuf>,
+    pub page_size: usize,
+    pub columns: usize,
+    pub scrape_concurrency: usize,
+    pub scrape_timeout_secs: u64,
+    pub link_check_timeout_secs: u64,
+    pub archive_timeout_secs: u64,
+    pub window_width: u32,
+    pub window_height: u32,
+    pub theme: Theme,
+    pub verbose: bool,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        UserConfig {
+            bookmarks_file: None,
+            browser: None,
+            profile: None,
+            cache_dir: None,
+            page_size: 12,
+            columns: 2,
+            scrape_concurrency: 16,
+            scrape_timeout_secs: 30,
+            link_check_timeout_secs: 20,
+            archive_timeout_secs: 30,
+            window_width: 1200,
+            window_height: 900,
+            theme: Theme::Dark,
+            verbose: false,
+        }
+    }
+}
+
+/// what the command line asked for besides the settings
+#[derive(Debug, Clone, Default)]
+pub struct Startup {
+    pub config: UserConfig,
+    /// the config file, env and flags problems, shown once the window is up
+    pub problems: Vec<String>,
+    pub show_help: bool,
+}
+
+fn parse_number<T: FromStr>(key: &str, val: &str) -> Result<T, String> {
+    val.trim()
+        .parse::<T>()
+        .map_err(|_| format!("{} should be a whole number, got \"{}\"", key, val))
+}
+
+fn parse_bool(key: &str, val: &str) -> Result<bool, String> {
+    match val.trim().to_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Ok(true),
+        "false" | "0" | "no" | "off" => Ok(false),
+        _ => Err(format!("{} should be true or false, got \"{}\"", key, val)),
+    }
+}
+
+fn non_empty(val: &str) -> Option<String> {
+    let val = val.trim();
+    if val.is_empty() {
+        None
+    } else {
+        Some(val.to_string())
+    }
+}
+
+impl UserConfig {
+    /// sets a key by its config file name, used for the env variables and the flags
+    pub fn set(&mut self, key: &str, val: &str) -> Result<(), String> {
+        match key {
+            "bookmarks_file" => self.bookmarks_file = non_empty(val).map(PathBuf::from),
+            "browser" => self.browser = non_empty(val),
+            "profile" => self.profile = non_empty(val),
+            "cache_dir" => self.cache_dir = non_empty(val).map(PathBuf::from),
+            "page_size" => self.page_size = parse_number(key, val)?,
+            "columns" => self.columns = parse_number(key, val)?,
+            "scrape_concurrency" => self.scrape_concurrency = parse_number(key, val)?,
+            "scrape_timeout_secs" | "scrape_timeout" => self.scrape_timeout_secs = parse_number(key, val)?,
+            "link_check_timeout_secs" | "link_check_timeout" => self.link_check_timeout_secs = parse_number(key, val)?,
+            "archive_timeout_secs" | "archive_timeout" => self.archive_timeout_secs = parse_number(key, val)?,
+            "window_width" => self.window_width = parse_number(key, val)?,
+            "window_height" => self.window_height = parse_number(key, val)?,
+            "theme" => self.theme = val.parse()?,
+            "verbose" => self.verbose = parse_bool(key, val)?,
+            _ => return Err(format!("unknown option {}", key)),
+        }
+        Ok(())
+    }
+    /// puts the default back for every value out of range and says what was wrong
+    pub fn validate(&mut self) -> Vec<String> {
+        let defaults = UserConfig::default();
+        let mut problems: Vec<String> = Vec::new();
+        if let Some(path) = &self.bookmarks_file {
+            if !path.is_file() {
+                problems.push(format!("bookmarks_file {} doesn't exist", path.display()));
+                self.bookmarks_file = None;
+            }
+        }
+        if let Some(path) = &self.cache_dir {
+            if path.exists() && !path.is_dir() {
+                problems.push(format!("cache_dir {} is not a directory", path.display()));
+                self.cache_dir = None;
+            }
+        }
+        if self.page_size == 0 || self.page_size > MAX_PAGE_SIZE {
+            problems.push(format!("page_size should be between 1 and {}, got {}", MAX_PAGE_SIZE, self.page_size));
+            self.page_size = defaults.page_size;
+        }
+        if self.columns == 0 || self.columns > MAX_COLUMNS {
+            problems.push(format!("columns should be between 1 and {}, got {}", MAX_COLUMNS, self.columns));
+            self.columns = defaults.columns;
+        }
+        if self.scrape_concurrency == 0 {
+            problems.push(String::from("scrape_concurrency should be at least 1"));
+            self.scrape_concurrency = defaults.scrape_concurrency;
+        }
+        for (key, secs, default) in [
+            ("scrape_timeout_secs", &mut self.scrape_timeout_secs, defaults.scrape_timeout_secs),
+            ("link_check_timeout_secs", &mut self.link_check_timeout_secs, defaults.link_check_timeout_secs),
+            ("archive_timeout_secs", &mut self.archive_timeout_secs, defaults.archive_timeout_secs),
+        ] {
+            if *secs == 0 {
+                problems.push(format!("{} should be at least 1", key));
+                *secs = default;
+            }
+        }
+        if self.window_width < MIN_WINDOW_WIDTH {
+            problems.push(format!("window_width should be at least {}, got {}", MIN_WINDOW_WIDTH, self.window_width));
+            self.window_width = defaults.window_width;
+        }
+        if self.window_height < MIN_WINDOW_HEIGHT {
+            problems.push(format!("window_height should be at least {}, got {}", MIN_WINDOW_HEIGHT, self.window_height));
+            self.window_height = defaults.window_height;
+        }
+        problems
+    }
+}
+
+pub fn get_default_config_file_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(CONFIG_FILE_PATH))
+}
+
+/// a missing file is the same as an empty one
+fn read_config_file(path: &PathBuf, problems: &mut Vec<String>) -> UserConfig {
+    match fs::read_to_string(path) {
+        Ok(content) => parse_config_file(&content, path, problems),
+        Err(_) => UserConfig::default(),
+    }
+}
+
+/// every key goes through set like the env variables and flags, so an unknown key or a bad value
+/// only costs that key its default, a file that isn't toml at all is reported and the defaults are used
+fn parse_config_file(content: &str, path: &PathBuf, problems: &mut Vec<String>) -> UserConfig {
+    let mut config = UserConfig::default();
+    let table: toml::value::Table = match toml::from_str(content) {
+        Ok(table) => table,
+        Err(error) => {
+            problems.push(format!("{} can't be read, the defaults are used: {}", path.display(), error));
+            return config;
+        }
+    };
+    for (key, val) in table.iter() {
+        let val = match val {
+            toml::Value::String(val) => val.clone(),
+            toml::Value::Integer(val) => val.to_string(),
+            toml::Value::Boolean(val) => val.to_string(),
+            other => {
+                problems.push(format!("{} in {}: a {} can't be used, the default is kept", key, path.display(), other.type_str()));
+                continue;
+            }
+        };
+        if let Err(problem) = config.set(key, &val) {
+            problems.push(format!("{} in {}: {}, the default is kept", key, path.display(), problem));
+        }
+    }
+    config
+}
+
+/// "--page-size 24" and "--page-size=24" both become ("page_size", "24")
+fn parse_args(args: &[String], problems: &mut Vec<String>, show_help: &mut bool) -> Vec<(String, String)> {
+    let mut options: Vec<(String, String)> = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let flag = match arg.strip_prefix("--") {
+            Some(flag) => flag,
+            None => {
+                problems.push(format!("unexpected argument {}", arg));
+                continue;
+            }
+        };
+        if flag == "help" {
+            *show_help = true;
+            continue;
+        }
+        // the only flag that needs no value
+        if flag == "verbose" {
+            options.push((String::from("verbose"), String::from("true")));
+            continue;
+        }
+        let (key, val) = match flag.split_once('=') {
+            Some((key, val)) => (key.to_string(), Some(val.to_string())),
+            None => (flag.to_string(), args.next().cloned()),
+        };
+        match val {
+            Some(val) => options.push((key.replace('-', "_"), val)),
+            None => problems.push(format!("--{} needs a value", key)),
+        }
+    }
+    options
+}
+
+/// the config file, then the ROBOZA_* env variables, then the flags
+pub fn load(args: &[String]) -> Startup {
+    let mut problems: Vec<String> = Vec::new();
+    let mut show_help = false;
+    let options = parse_args(args, &mut problems, &mut show_help);
+    let config_path = options
+        .iter()
+        .rev()
+        .find(|(key, _)| key == "config")
+        .map(|(_, val)| PathBuf::from(val))
+        .or_else(|| env::var(format!("{}CONFIG", ENV_PREFIX)).ok().map(PathBuf::from));
+    let mut config = match &config_path {
+        Some(path) if !path.is_file() => {
+            problems.push(format!("config file {} doesn't exist, the defaults are used", path.display()));
+            UserConfig::default()
+        }
+        Some(path) => read_config_file(path, &mut problems),
+        None => get_default_config_file_path()
+            .map(|path| read_config_file(&path, &mut problems))
+            .unwrap_or_default(),
+    };
+    let mut env_vars: Vec<(String, String)> = env::vars()
+        .filter_map(|(name, val)| name.strip_prefix(ENV_PREFIX).map(|key| (key.to_lowercase(), val)))
+        .filter(|(key, _)| key != "config")
+        .collect();
+    env_vars.sort();
+    for (key, val) in env_vars.iter() {
+        if let Err(problem) = config.set(key, val) {
+            problems.push(format!("{}{}: {}", ENV_PREFIX, key.to_uppercase(), problem));
+        }
+    }
+    for (key, val) in options.iter().filter(|(key, _)| key != "config") {
+        if let Err(problem) = config.set(key, val) {
+            problems.push(format!("--{}: {}", key.replace('_', "-"), problem));
+        }
+    }
+    problems.extend(config.validate());
+    Startup {
+        config,
+        problems,
+        show_help,
+    }
+}
+
+/// keeps the config for the rest of the run, only the first call counts
+pub fn init(config: UserConfig) {
+    USER_CONFIG.set(config).ok();
+}
+
+/// the defaults until init is called
+pub fn get() -> &'static UserConfig {
+    USER_CONFIG.get_or_init(UserConfig::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> (UserConfig, Vec<String>) {
+        let mut problems: Vec<String> = Vec::new();
+        let config = parse_config_file(content, &PathBuf::from("config.toml"), &mut problems);
+        (config, problems)
+    }
+
+    #[test]
+    fn reads_every_key() {
+        let (config, problems) = parse("page_size = 24\ntheme = \"light\"\nverbose = true\nbrowser = \"chrome\"\n");
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(config.page_size, 24);
+        assert_eq!(config.theme, Theme::Light);
+        assert!(config.verbose);
+        assert_eq!(config.browser, Some(String::from("chrome")));
+    }
+
+    #[test]
+    fn keeps_the_good_keys_of_a_file_with_bad_ones() {
+        let (config, problems) = parse("page_size = 24\ncolums = 3\ntheme = \"blue\"\ncolumns = [1, 2]\nscrape_timeout_secs = \"soon\"\n");
+        let defaults = UserConfig::default();
+        assert_eq!(config.page_size, 24);
+        assert_eq!(config.theme, defaults.theme);
+        assert_eq!(config.columns, defaults.columns);
+        assert_eq!(config.scrape_timeout_secs, defaults.scrape_timeout_secs);
+        assert_eq!(problems.len(), 4, "{:?}", problems);
+        assert!(problems.iter().any(|problem| problem.contains("unknown option colums")));
+    }
+
+    #[test]
+    fn uses_the_defaults_for_a_broken_file() {
+        let (config, problems) = parse("page_size = = 24");
+        assert_eq!(config, UserConfig::default());
+        assert_eq!(problems.len(), 1);
+    }
+}
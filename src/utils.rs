@@ -1,22 +1,196 @@
-#[derive(Debug, Clone)]
-pub enum Error {
-    APIError,
-    LanguageError,
+use std::fmt;
+
+/// what kind of thing went wrong, the toast and the error log group by it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Io,
+    Parse,
+    Network,
+    Timeout,
+    /// the marks cache database
+    Cache,
+    ImageDecode,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Io => write!(f, "file"),
+            ErrorKind::Parse => write!(f, "parse"),
+            ErrorKind::Network => write!(f, "network"),
+            ErrorKind::Timeout => write!(f, "timeout"),
+            ErrorKind::Cache => write!(f, "cache"),
+            ErrorKind::ImageDecode => write!(f, "image"),
+        }
+    }
+}
+
+/// a failure with its cause and, when known, the bookmark and url it happened on
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub bookmark_id: Option<String>,
+    pub url: Option<String>,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Error {
+            kind,
+            message: message.into(),
+            bookmark_id: None,
+            url: None,
+        }
+    }
+    pub fn with_bookmark(mut self, bookmark_id: &str) -> Self {
+        self.bookmark_id = Some(bookmark_id.to_string());
+        self
+    }
+    /// keeps the url the cause already carries, reqwest knows the one after the redirects
+    pub fn with_url(mut self, url: &str) -> Self {
+        if self.url.is_none() {
+            self.url = Some(url.to_string());
+        }
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} error: {}", self.kind, self.message)?;
+        if let Some(bookmark_id) = &self.bookmark_id {
+            write!(f, " (bookmark {})", bookmark_id)?;
+        }
+        if let Some(url) = &self.url {
+            write!(f, " at {}", url)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// the message of an error followed by the messages of its causes
+pub fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut chain = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        chain.push_str(": ");
+        chain.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    chain
 }
+
 impl From<reqwest::Error> for Error {
     fn from(error: reqwest::Error) -> Error {
-        dbg!(error);
-
-        Error::APIError
+        let kind = if error.is_timeout() { ErrorKind::Timeout } else { ErrorKind::Network };
+        Error {
+            kind,
+            message: error_chain(&error),
+            bookmark_id: None,
+            url: error.url().map(|url| url.to_string()),
+        }
     }
 }
 impl From<link_preview::fetch::Error> for Error{
     fn from(error: link_preview::fetch::Error) -> Error {
-        dbg!(error);
-        Error::APIError
+        Error::new(ErrorKind::Network, format!("{:?}", error))
+    }
+}
+impl From<rusqlite::Error> for Error {
+    fn from(error: rusqlite::Error) -> Error {
+        Error::new(ErrorKind::Cache, error_chain(&error))
+    }
+}
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Error {
+        Error::new(ErrorKind::Parse, error.to_string())
     }
 }
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Error {
+        let kind = if error.kind() == std::io::ErrorKind::TimedOut { ErrorKind::Timeout } else { ErrorKind::Io };
+        Error::new(kind, error.to_string())
+    }
+}
+
+// chrome counts microseconds since 1601-01-01
+const WINDOWS_TO_UNIX_EPOCH_SECS: i64 = 11_644_473_600;
+
+/// converts a chrome timestamp string to unix seconds, "0" and garbage mean there is no date
+pub fn chrome_time_to_unix(val: &str) -> Option<i64> {
+    match val.trim().parse::<i64>() {
+        Ok(micros) if micros > 0 => Some(micros / 1_000_000 - WINDOWS_TO_UNIX_EPOCH_SECS),
+        _ => None,
+    }
+}
+
+pub fn format_date(unix_secs: i64) -> String {
+    chrono::DateTime::from_timestamp(unix_secs, 0)
+        .map(|date| date.naive_utc().format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+/// parses a YYYY-MM-DD date into the unix seconds of its first (or last) second
+pub fn parse_date(val: &str, end_of_day: bool) -> Option<i64> {
+    let date = chrono::NaiveDate::parse_from_str(val.trim(), "%Y-%m-%d").ok()?;
+    let time = if end_of_day { date.and_hms_opt(23, 59, 59)? } else { date.and_hms_opt(0, 0, 0)? };
+    Some(time.and_utc().timestamp())
+}
+
+/// the host of a link without "www.", empty when the link can't be parsed
+pub fn domain_of(link: &str) -> String {
+    reqwest::Url::parse(link)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.trim_start_matches("www.").to_lowercase()))
+        .unwrap_or_default()
+}
 
+/// the number of single char edits between two words
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current: Vec<usize> = vec![0; b.len() + 1];
+    for (i, a_char) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + if a_char == *b_char { 0 } else { 1 };
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// a size in bytes the way file managers show it, e.g. "12.3 MB"
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// a rough wait the way a progress bar says it, e.g. "about 3 min"
+pub fn format_eta(eta: std::time::Duration) -> String {
+    let secs = eta.as_secs();
+    if secs < 60 {
+        format!("about {} s", secs.max(1))
+    } else if secs < 3600 {
+        format!("about {} min", (secs + 30) / 60)
+    } else {
+        format!("about {} h {} min", secs / 3600, secs % 3600 / 60)
+    }
+}
 
 pub fn truncate_with_dots(s: &str, max_chars: usize) -> String {
     match s.char_indices().nth(max_chars) {
@@ -30,3 +204,25 @@ pub fn truncate_with_dots(s: &str, max_chars: usize) -> String {
         },
     }
 }
+
+/// the file extension of an image from its first bytes, None when it isn't an image the cards can show
+pub fn image_extension(bytes: &[u8]) -> Option<&'static str> {
+    let head = &bytes[..bytes.len().min(512)];
+    if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("png")
+    } else if head.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("jpg")
+    } else if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        Some("gif")
+    } else if head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+        Some("webp")
+    } else if head.starts_with(b"BM") {
+        Some("bmp")
+    } else if head.starts_with(&[0, 0, 1, 0]) {
+        Some("ico")
+    } else if String::from_utf8_lossy(head).to_lowercase().contains("<svg") {
+        Some("svg")
+    } else {
+        None
+    }
+}